@@ -18,7 +18,7 @@ async fn main() -> anyhow::Result<()> {
 
     // Grab the client secret, convert to a `ClientSecret` with the `new` method.
     let client_secret = get_env_or_arg("TWITCH_CLIENT_SECRET", &mut args)
-        .map(twitch_oauth2::ClientSecret::new)
+        .map(twitch_oauth2::ClientSecret::from)
         .expect("Please set env: TWITCH_CLIENT_SECRET or pass client secret as an argument");
 
     // Get the app access token
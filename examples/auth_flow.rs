@@ -24,7 +24,7 @@ async fn main() -> anyhow::Result<()> {
 
     // Grab the client secret, convert to a `ClientSecret` with the `new` method.
     let client_secret = get_env_or_arg("TWITCH_CLIENT_SECRET", &mut args)
-        .map(twitch_oauth2::ClientSecret::new)
+        .map(twitch_oauth2::ClientSecret::from)
         .context("Please set env: TWITCH_CLIENT_SECRET or pass as second argument")?;
 
     // Grab the redirect URL, this has to be set verbatim in the developer console: https://dev.twitch.tv/console/apps/
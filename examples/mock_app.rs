@@ -17,7 +17,7 @@ async fn main() -> anyhow::Result<()> {
         .expect("Please set env: MOCK_CLIENT_ID or pass client id as an argument");
 
     let client_secret = get_env_or_arg("MOCK_CLIENT_SECRET", &mut args)
-        .map(twitch_oauth2::ClientSecret::new)
+        .map(twitch_oauth2::ClientSecret::from)
         .expect("Please set env: MOCK_CLIENT_SECRET or pass client secret as an argument");
 
     // Getting an app access token from twitch-cli mock is almost exactly the same as in production, just using a different url.
@@ -15,16 +15,16 @@ async fn main() -> anyhow::Result<()> {
 
     // Grab the token, convert to a `AccessToken` with the `new` method.
     let user_token = get_env_or_arg("TWITCH_TOKEN", &mut args)
-        .map(twitch_oauth2::AccessToken::new)
+        .map(twitch_oauth2::AccessToken::from)
         .expect("Please set env: TWITCH_TOKEN or pass token as first argument");
 
     // Grab refresh token, not necessarily required.
     let refresh_token =
-        get_env_or_arg("TWITCH_REFRESH_TOKEN", &mut args).map(twitch_oauth2::RefreshToken::new);
+        get_env_or_arg("TWITCH_REFRESH_TOKEN", &mut args).map(twitch_oauth2::RefreshToken::from);
 
     // Grab the client secret, not necessarily required, unless you have a refresh token and want to refresh the token with `UserToken::refresh`.
     let client_secret =
-        get_env_or_arg("TWITCH_CLIENT_SECRET", &mut args).map(twitch_oauth2::ClientSecret::new);
+        get_env_or_arg("TWITCH_CLIENT_SECRET", &mut args).map(twitch_oauth2::ClientSecret::from);
 
     let token =
         twitch_oauth2::UserToken::from_existing(&reqwest, user_token, refresh_token, client_secret)
@@ -4,6 +4,7 @@
 
 use std::error::Error;
 use std::future::Future;
+use std::pin::Pin;
 
 /// The User-Agent `product` of this crate.
 pub static TWITCH_OAUTH2_USER_AGENT: &str =
@@ -18,6 +19,243 @@ pub trait Client: Sync + Send {
         &self,
         request: http::Request<Vec<u8>>,
     ) -> impl Future<Output = Result<http::Response<Vec<u8>>, <Self as Client>::Error>> + Send + use<Self>;
+
+    /// Get the observer of token lifecycle events for tokens used with this client.
+    ///
+    /// Returns `None` by default, wrap a client in [`ObservedClient`](crate::tokens::events::ObservedClient) to set one.
+    fn token_events(&self) -> Option<&dyn crate::tokens::events::TokenEvents> { None }
+}
+
+/// A boxed future, returned by [`ErasedClient`] and [`DynTwitchToken`](crate::tokens::DynTwitchToken)
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// An object safe version of [`Client`], to use clients as `dyn ErasedClient`.
+///
+/// Implemented for all clients, and `&dyn ErasedClient` implements [`Client`].
+pub trait ErasedClient: Sync + Send {
+    /// Send a request, see [`Client::req`]
+    fn req_erased(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> BoxFuture<'static, Result<http::Response<Vec<u8>>, ErasedClientError>>;
+
+    /// Get the observer of token lifecycle events, see [`Client::token_events`]
+    fn token_events_erased(&self) -> Option<&dyn crate::tokens::events::TokenEvents>;
+}
+
+impl<C: Client + 'static> ErasedClient for C {
+    fn req_erased(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> BoxFuture<'static, Result<http::Response<Vec<u8>>, ErasedClientError>> {
+        let fut = self.req(request);
+        Box::pin(async move { fut.await.map_err(|e| ErasedClientError(Box::new(e))) })
+    }
+
+    fn token_events_erased(&self) -> Option<&dyn crate::tokens::events::TokenEvents> {
+        self.token_events()
+    }
+}
+
+impl<'a, 'b> Client for &'a (dyn ErasedClient + 'b) {
+    type Error = ErasedClientError;
+
+    fn req(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> impl Future<Output = Result<http::Response<Vec<u8>>, Self::Error>> + Send + use<'a, 'b>
+    {
+        (**self).req_erased(request)
+    }
+
+    fn token_events(&self) -> Option<&dyn crate::tokens::events::TokenEvents> {
+        (**self).token_events_erased()
+    }
+}
+
+/// A type erased [`Client`], to not be generic over the client.
+///
+/// Cloning is cheap and the clones share the client.
+///
+/// # Examples
+///
+/// ```rust
+/// # async move {
+/// # let client = twitch_oauth2::client::DummyClient; stringify!(
+/// let client = reqwest::Client::new();
+/// # );
+/// use twitch_oauth2::{client::DynClient, AccessToken};
+///
+/// let client = DynClient::new(client);
+/// let token = AccessToken::from("sometokenherewhichisvalidornot");
+/// let validated = token.validate_token(&client).await?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// # };
+/// ```
+#[derive(Clone)]
+pub struct DynClient(std::sync::Arc<dyn ErasedClient>);
+
+impl DynClient {
+    /// Erase the type of a client
+    pub fn new<C: Client + 'static>(client: C) -> Self { Self(std::sync::Arc::new(client)) }
+}
+
+impl From<std::sync::Arc<dyn ErasedClient>> for DynClient {
+    fn from(client: std::sync::Arc<dyn ErasedClient>) -> Self { Self(client) }
+}
+
+impl std::fmt::Debug for DynClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("DynClient").finish_non_exhaustive()
+    }
+}
+
+impl Client for DynClient {
+    type Error = ErasedClientError;
+
+    fn req(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> impl Future<Output = Result<http::Response<Vec<u8>>, Self::Error>> + Send + use<> {
+        self.0.req_erased(request)
+    }
+
+    fn token_events(&self) -> Option<&dyn crate::tokens::events::TokenEvents> {
+        self.0.token_events_erased()
+    }
+}
+
+/// A [`Client`] that waits for the [rate limit](crate::id::RateLimit) of `id.twitch.tv` to reset when the bucket is exhausted.
+///
+/// The rate limit is taken from the responses of twitch, and requests are counted against it until the next response.
+/// Waiting is done with the given sleep function, like `tokio::time::sleep`.
+///
+/// Cloning is cheap and the clones share the rate limit.
+///
+/// # Examples
+///
+/// ```rust
+/// # async move {
+/// # let client = twitch_oauth2::client::DummyClient; stringify!(
+/// let client = reqwest::Client::new();
+/// # );
+/// use twitch_oauth2::{client::RateLimitedClient, AccessToken};
+///
+/// let client = RateLimitedClient::new(client, tokio::time::sleep);
+/// let token = AccessToken::from("sometokenherewhichisvalidornot");
+/// let validated = token.validate_token(&client).await?;
+/// println!("{:?}", client.rate_limit());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// # };
+/// ```
+pub struct RateLimitedClient<C> {
+    inner: std::sync::Arc<RateLimited<C>>,
+}
+
+type Sleep = Box<dyn Fn(std::time::Duration) -> BoxFuture<'static, ()> + Send + Sync>;
+
+struct RateLimited<C> {
+    client: C,
+    sleep: Sleep,
+    rate_limit: std::sync::Mutex<Option<crate::id::RateLimit>>,
+}
+
+impl<C: Client> RateLimitedClient<C> {
+    /// Wrap a client, waiting with `sleep` when the rate limit is exhausted
+    pub fn new<F, Fut>(client: C, sleep: F) -> Self
+    where
+        F: Fn(std::time::Duration) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static, {
+        Self {
+            inner: std::sync::Arc::new(RateLimited {
+                client,
+                sleep: Box::new(move |duration| Box::pin(sleep(duration))),
+                rate_limit: std::sync::Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Get the wrapped client
+    pub fn client(&self) -> &C { &self.inner.client }
+
+    /// Get the last known rate limit, with the requests sent since counted against it
+    pub fn rate_limit(&self) -> Option<crate::id::RateLimit> { *self.inner.lock() }
+}
+
+impl<C> RateLimited<C> {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<crate::id::RateLimit>> {
+        self.rate_limit.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Take a request from the bucket, or return how long to wait for it to be refilled
+    fn reserve(&self) -> Option<std::time::Duration> {
+        let mut rate_limit = self.lock();
+        let rate_limit = rate_limit.as_mut()?;
+        let reset_in = rate_limit.reset_in();
+        if reset_in.is_zero() {
+            // refilled, count against a full bucket until the next response tells us more. Twitch refills buckets every minute
+            rate_limit.remaining = rate_limit.limit;
+            rate_limit.reset = std::time::SystemTime::now() + std::time::Duration::from_secs(60);
+        } else if rate_limit.is_exhausted() {
+            return Some(reset_in);
+        }
+        rate_limit.remaining = rate_limit.remaining.saturating_sub(1);
+        None
+    }
+}
+
+impl<C> Clone for RateLimitedClient<C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<C: std::fmt::Debug> std::fmt::Debug for RateLimitedClient<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimitedClient")
+            .field("client", &self.inner.client)
+            .field("rate_limit", &*self.inner.lock())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C: Client> Client for RateLimitedClient<C> {
+    type Error = C::Error;
+
+    fn req(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> impl Future<Output = Result<http::Response<Vec<u8>>, Self::Error>> + Send + use<C> {
+        let inner = self.inner.clone();
+        async move {
+            if let Some(wait) = inner.reserve() {
+                (inner.sleep)(wait).await;
+                // the bucket was refilled while waiting, take the request from it
+                inner.reserve();
+            }
+            let response = inner.client.req(request).await?;
+            if let Some(rate_limit) = crate::id::RateLimit::from_response(&response) {
+                *inner.lock() = Some(rate_limit);
+            }
+            Ok(response)
+        }
+    }
+
+    fn token_events(&self) -> Option<&dyn crate::tokens::events::TokenEvents> {
+        self.inner.client.token_events()
+    }
+}
+
+/// Error returned by an [`ErasedClient`], wrapping the error of the client
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub struct ErasedClientError(pub Box<dyn Error + Send + Sync + 'static>);
+
+impl ErasedClientError {
+    /// Get the error of the client, if it is of type `E`
+    pub fn downcast_ref<E: Error + 'static>(&self) -> Option<&E> { self.0.downcast_ref() }
 }
 
 #[doc(hidden)]
@@ -74,11 +312,81 @@ impl Client for ReqwestClient {
     }
 }
 
-#[cfg(all(feature = "reqwest", test))]
+/// Client and tokens for tests
+#[cfg(all(test, feature = "client"))]
+pub(crate) mod mock {
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    use super::Client;
+    use crate::{AccessToken, UserToken};
+
+    /// Client recording the requests it gets, answering them with the queued responses.
+    ///
+    /// Answers with an empty `200 OK` when no responses are queued.
+    #[derive(Debug, Clone, Default)]
+    pub(crate) struct MockClient {
+        responses: Arc<Mutex<VecDeque<http::Response<Vec<u8>>>>>,
+        requests: Arc<Mutex<Vec<http::Request<Vec<u8>>>>>,
+    }
+
+    impl MockClient {
+        /// Queue a response
+        pub(crate) fn respond(self, status: u16, body: impl Into<Vec<u8>>) -> Self {
+            let response = http::Response::builder()
+                .status(status)
+                .body(body.into())
+                .unwrap();
+            self.responses.lock().unwrap().push_back(response);
+            self
+        }
+
+        /// Uris of the requests sent so far
+        pub(crate) fn uris(&self) -> Vec<String> {
+            self.requests
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|r| r.uri().to_string())
+                .collect()
+        }
+    }
+
+    impl Client for MockClient {
+        type Error = std::convert::Infallible;
+
+        fn req(
+            &self,
+            request: http::Request<Vec<u8>>,
+        ) -> impl std::future::Future<Output = Result<http::Response<Vec<u8>>, Self::Error>> + Send + use<>
+        {
+            self.requests.lock().unwrap().push(request);
+            let response = self.responses.lock().unwrap().pop_front();
+            std::future::ready(Ok(response.unwrap_or_default()))
+        }
+    }
+
+    /// A user token for `justintv` with the access token `token`
+    pub(crate) fn user_token(client_id: &str) -> UserToken {
+        UserToken::from_existing_unchecked(
+            AccessToken::from("token"),
+            None,
+            client_id,
+            None,
+            "justintv".into(),
+            "1337".into(),
+            None,
+            None,
+        )
+    }
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
+    #[cfg(feature = "reqwest")]
     /// Test that the returned future does not depend on the lifetime of `self`
     fn reqwest_capture() {
         fn inner() -> impl Future<Output = Result<http::Response<Vec<u8>>, reqwest::Error>> + Send {
@@ -87,4 +395,102 @@ mod tests {
         }
         let _fut = inner();
     }
+
+    #[tokio::test]
+    #[cfg(feature = "reqwest")]
+    async fn dyn_client() {
+        fn inner() -> impl Future<Output = Result<http::Response<Vec<u8>>, ErasedClientError>> + Send
+        {
+            let client = DynClient::new(ReqwestClient::new());
+            client.req(http::Request::new(vec![]))
+        }
+        let _fut = inner();
+
+        let client = DynClient::new(DummyClient);
+        let error = client
+            .clone()
+            .req(http::Request::new(vec![]))
+            .await
+            .unwrap_err();
+        assert!(error.downcast_ref::<DummyClient>().is_some());
+    }
+
+    #[derive(Debug)]
+    struct RateLimitHeaders(std::sync::atomic::AtomicU64);
+
+    impl Client for RateLimitHeaders {
+        type Error = std::convert::Infallible;
+
+        fn req(
+            &self,
+            _: http::Request<Vec<u8>>,
+        ) -> impl Future<Output = Result<http::Response<Vec<u8>>, Self::Error>> + Send + use<>
+        {
+            use std::sync::atomic::Ordering;
+            let remaining = self.0.load(Ordering::SeqCst).saturating_sub(1);
+            self.0.store(remaining, Ordering::SeqCst);
+            let reset = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                + std::time::Duration::from_secs(60);
+            let response = http::Response::builder()
+                .header("Ratelimit-Limit", "800")
+                .header("Ratelimit-Remaining", remaining.to_string())
+                .header("Ratelimit-Reset", reset.as_secs().to_string())
+                .body(vec![])
+                .unwrap();
+            std::future::ready(Ok(response))
+        }
+    }
+
+    #[tokio::test]
+    async fn rate_limited() {
+        let waits = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let client = RateLimitedClient::new(RateLimitHeaders(2.into()), {
+            let waits = waits.clone();
+            move |duration| {
+                waits.lock().unwrap().push(duration);
+                std::future::ready(())
+            }
+        });
+        assert!(client.rate_limit().is_none());
+
+        client.req(http::Request::default()).await.unwrap();
+        assert_eq!(client.rate_limit().unwrap().remaining, 1);
+        client.req(http::Request::default()).await.unwrap();
+        assert!(waits.lock().unwrap().is_empty());
+        assert_eq!(client.rate_limit().unwrap().remaining, 0);
+        client.req(http::Request::default()).await.unwrap();
+        let waits = waits.lock().unwrap();
+        assert_eq!(waits.len(), 1);
+        assert!(waits[0] > std::time::Duration::from_secs(58));
+    }
+
+    #[tokio::test]
+    async fn rate_limited_after_reset() {
+        let waits = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let client = RateLimitedClient::new(mock::MockClient::default(), {
+            let waits = waits.clone();
+            move |duration| {
+                waits.lock().unwrap().push(duration);
+                std::future::ready(())
+            }
+        });
+        *client.inner.lock() = Some(crate::id::RateLimit {
+            limit: 2,
+            remaining: 0,
+            reset: std::time::SystemTime::now() - std::time::Duration::from_secs(1),
+        });
+
+        client.req(http::Request::default()).await.unwrap();
+        assert_eq!(client.rate_limit().unwrap().remaining, 1, "refilled");
+        client.req(http::Request::default()).await.unwrap();
+        assert!(waits.lock().unwrap().is_empty());
+        client.req(http::Request::default()).await.unwrap();
+        assert_eq!(
+            waits.lock().unwrap().len(),
+            1,
+            "requests after the reset are counted"
+        );
+    }
 }
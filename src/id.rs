@@ -4,6 +4,12 @@ use serde_derive::{Deserialize, Serialize};
 
 use crate::{AccessToken, RequestParseError};
 use std::time::Duration;
+
+#[cfg(not(all(target_family = "wasm", target_os = "unknown")))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
+use web_time::{SystemTime, UNIX_EPOCH};
 /// Twitch's representation of the oauth flow.
 ///
 /// Retrieve with
@@ -21,9 +27,16 @@ pub struct TwitchTokenResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub refresh_token: Option<crate::RefreshToken>,
     /// Scopes attached to token
-    #[serde(rename = "scope", deserialize_with = "scope::deserialize")]
+    #[serde(
+        rename = "scope",
+        deserialize_with = "scope::deserialize",
+        serialize_with = "scope::serialize"
+    )]
     #[serde(default)]
-    pub scopes: Option<Vec<crate::Scope>>,
+    pub scopes: Option<crate::ScopeSet>,
+    /// Rate limit of `id.twitch.tv` from the headers of the response, if it was created with [`TwitchTokenResponse::from_response`]
+    #[serde(skip)]
+    pub rate_limit: Option<RateLimit>,
 }
 
 impl TwitchTokenResponse {
@@ -31,7 +44,9 @@ impl TwitchTokenResponse {
     pub fn from_response<B: AsRef<[u8]>>(
         response: &http::Response<B>,
     ) -> Result<TwitchTokenResponse, RequestParseError> {
-        crate::parse_response(response)
+        let mut token: TwitchTokenResponse = crate::parse_response(response)?;
+        token.rate_limit = RateLimit::from_response(response);
+        Ok(token)
     }
 
     /// Get the access token from this response
@@ -44,7 +59,7 @@ impl TwitchTokenResponse {
     pub fn refresh_token(&self) -> Option<&crate::RefreshTokenRef> { self.refresh_token.as_deref() }
 
     /// Get the scopes from this response
-    pub fn scopes(&self) -> Option<&[crate::Scope]> { self.scopes.as_deref() }
+    pub fn scopes(&self) -> Option<&crate::ScopeSet> { self.scopes.as_ref() }
 }
 
 /// Twitch's representation of the oauth flow for errors
@@ -72,6 +87,64 @@ impl std::fmt::Display for TwitchTokenErrorResponse {
         )
     }
 }
+/// Rate limit of `id.twitch.tv`, from the `Ratelimit-*` headers of a response
+///
+/// Requests are taken from a bucket that is refilled at [`reset`](RateLimit::reset).
+/// When the bucket is exhausted, twitch responds with `429 Too Many Requests` and [`RequestParseError::RateLimited`].
+///
+/// # Examples
+///
+/// ```rust
+/// use twitch_oauth2::id::RateLimit;
+///
+/// let response = http::Response::builder()
+///     .header("Ratelimit-Limit", "800")
+///     .header("Ratelimit-Remaining", "799")
+///     .header("Ratelimit-Reset", "1700000000")
+///     .body(Vec::<u8>::new())?;
+/// let rate_limit = RateLimit::from_response(&response).unwrap();
+/// assert_eq!(rate_limit.remaining, 799);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Number of requests the bucket holds
+    pub limit: u64,
+    /// Number of requests left in the bucket
+    pub remaining: u64,
+    /// When the bucket is refilled
+    pub reset: SystemTime,
+}
+
+impl RateLimit {
+    /// Parse the `Ratelimit-Limit`, `Ratelimit-Remaining` and `Ratelimit-Reset` headers
+    ///
+    /// Returns `None` if any of the headers is missing or invalid.
+    pub fn from_headers(headers: &http::HeaderMap) -> Option<RateLimit> {
+        let header = |name: &str| -> Option<u64> { headers.get(name)?.to_str().ok()?.parse().ok() };
+        Some(RateLimit {
+            limit: header("ratelimit-limit")?,
+            remaining: header("ratelimit-remaining")?,
+            reset: UNIX_EPOCH + Duration::from_secs(header("ratelimit-reset")?),
+        })
+    }
+
+    /// Parse the rate limit headers of a response, see [`RateLimit::from_headers`]
+    pub fn from_response<B>(response: &http::Response<B>) -> Option<RateLimit> {
+        Self::from_headers(response.headers())
+    }
+
+    /// Returns true if there are no requests left in the bucket
+    pub fn is_exhausted(&self) -> bool { self.remaining == 0 }
+
+    /// Time until the bucket is refilled, zero if it already has been
+    pub fn reset_in(&self) -> Duration {
+        self.reset
+            .duration_since(SystemTime::now())
+            .unwrap_or_default()
+    }
+}
+
 /// Response from the device code flow
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DeviceCodeResponse {
@@ -115,19 +188,28 @@ pub mod status_code {
 
 #[doc(hidden)]
 pub mod scope {
-    use serde::{de::Deserialize, Deserializer};
+    use serde::{de::Deserialize, Deserializer, Serializer};
 
-    pub fn deserialize<'de, D>(de: D) -> Result<Option<Vec<crate::Scope>>, D::Error>
+    pub fn deserialize<'de, D>(de: D) -> Result<Option<crate::ScopeSet>, D::Error>
     where D: Deserializer<'de> {
         let scopes: Option<Vec<crate::Scope>> = Deserialize::deserialize(de)?;
         if let Some(scopes) = scopes {
             match scopes {
-                scopes if scopes.is_empty() || scopes.len() > 1 => Ok(Some(scopes)),
+                scopes if scopes.is_empty() || scopes.len() > 1 => Ok(Some(scopes.into())),
                 scopes if scopes.len() == 1 && scopes.first().unwrap().as_str() == "" => Ok(None),
-                _ => Ok(Some(scopes)),
+                _ => Ok(Some(scopes.into())),
             }
         } else {
             Ok(None)
         }
     }
+
+    /// Serialize as a sequence, like twitch does
+    pub fn serialize<S>(scopes: &Option<crate::ScopeSet>, ser: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        match scopes {
+            Some(scopes) => ser.collect_seq(scopes),
+            None => ser.serialize_none(),
+        }
+    }
 }
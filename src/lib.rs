@@ -20,7 +20,7 @@
 //!     .redirect(reqwest::redirect::Policy::none())
 //!     .build()?;
 //! # );
-//! let token = AccessToken::new("sometokenherewhichisvalidornot".to_string());
+//! let token = AccessToken::from("sometokenherewhichisvalidornot".to_string());
 //! let token = UserToken::from_token(&client, token).await?;
 //! println!("token: {:?}", token.token()); // prints `[redacted access token]`
 //! # Ok::<(), Box<dyn std::error::Error>>(())};
@@ -56,18 +56,35 @@
 //! or [`UserTokenBuilder::get_user_token_request`] and [`UserToken::from_response`]
 #[cfg(feature = "client")]
 pub mod client;
+#[cfg(feature = "extensions")]
+pub mod extensions;
 pub mod id;
 pub mod scopes;
+pub mod state;
 pub mod tokens;
+#[cfg(feature = "client")]
+mod trace;
 pub mod types;
+#[cfg(any(feature = "axum", feature = "actix", feature = "tower"))]
+pub mod web;
 
 use http::StatusCode;
 use id::TwitchTokenErrorResponse;
+#[cfg(all(
+    feature = "client",
+    not(all(target_family = "wasm", target_os = "unknown"))
+))]
+use std::time::Instant;
 #[cfg(feature = "client")]
 use tokens::errors::{RefreshTokenError, RevokeTokenError, ValidationError};
+#[cfg(all(feature = "client", all(target_family = "wasm", target_os = "unknown")))]
+use web_time::Instant;
 
 #[doc(inline)]
-pub use scopes::{Scope, Validator};
+pub use scopes::{Scope, ScopeDiff, ScopeLookup, ScopeSet, Validator};
+#[cfg(feature = "validation_cache")]
+#[doc(inline)]
+pub use tokens::ValidationCache;
 #[doc(inline)]
 pub use tokens::{
     AppAccessToken, DeviceUserTokenBuilder, ImplicitUserTokenBuilder, TwitchToken, UserToken,
@@ -166,7 +183,7 @@ impl AccessTokenRef {
     pub fn validate_token_request(&self) -> http::Request<Vec<u8>> {
         use http::{header::AUTHORIZATION, HeaderMap, Method};
 
-        let auth_header = format!("OAuth {}", self.secret());
+        let mut auth_header = format!("OAuth {}", self.secret());
         let mut headers = HeaderMap::new();
         headers.insert(
             AUTHORIZATION,
@@ -174,6 +191,7 @@ impl AccessTokenRef {
                 .parse()
                 .expect("Failed to parse header for validation"),
         );
+        wipe(&mut auth_header);
 
         crate::construct_request::<&[(String, String)], _, _>(
             &crate::VALIDATE_URL,
@@ -188,6 +206,15 @@ impl AccessTokenRef {
     ///
     /// Should be checked on regularly, according to <https://dev.twitch.tv/docs/authentication/validate-tokens/>
     #[cfg(feature = "client")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "validate_token",
+            skip_all,
+            fields(client_id, user_id, scopes),
+            err(level = "warn", Display)
+        )
+    )]
     pub async fn validate_token<C>(
         &self,
         client: &C,
@@ -197,34 +224,48 @@ impl AccessTokenRef {
     {
         let req = self.validate_token_request();
 
-        let resp = client.req(req).await.map_err(ValidationError::Request)?;
-        if resp.status() == StatusCode::UNAUTHORIZED {
-            return Err(ValidationError::NotAuthorized);
+        let start = Instant::now();
+        let result = async {
+            let resp = trace::send(client, req)
+                .await
+                .map_err(ValidationError::Request)?;
+            if resp.status() == StatusCode::UNAUTHORIZED {
+                return Err(ValidationError::NotAuthorized);
+            }
+            ValidatedToken::from_response(&resp).map_err(|v| v.into_other())
         }
-        ValidatedToken::from_response(&resp).map_err(|v| v.into_other())
+        .await;
+        tokens::events::emit(client, |events| {
+            events.validated(&tokens::events::ValidationEvent {
+                elapsed: start.elapsed(),
+                outcome: result
+                    .as_ref()
+                    .map_err(tokens::events::ErrorKind::validation),
+            })
+        });
+        let validated = result?;
+        trace::record_validated(&validated);
+        Ok(validated)
     }
 
     /// Get the request needed to revoke this token.
     pub fn revoke_token_request(&self, client_id: &ClientId) -> http::Request<Vec<u8>> {
-        use http::{HeaderMap, Method};
-        use std::collections::HashMap;
-        let mut params = HashMap::new();
-        params.insert("client_id", client_id.as_str());
-        params.insert("token", self.secret());
-
-        construct_request(
-            &crate::REVOKE_URL,
-            &params,
-            HeaderMap::new(),
-            Method::POST,
-            vec![],
-        )
+        revoke_request(self.secret(), None, client_id)
     }
 
     /// Revoke the token.
     ///
     /// See <https://dev.twitch.tv/docs/authentication/revoke-tokens/>
     #[cfg(feature = "client")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "revoke_token",
+            skip_all,
+            fields(client_id = %client_id),
+            err(level = "warn", Display)
+        )
+    )]
     pub async fn revoke_token<C>(
         &self,
         http_client: &C,
@@ -235,17 +276,98 @@ impl AccessTokenRef {
     {
         let req = self.revoke_token_request(client_id);
 
-        let resp = http_client
-            .req(req)
+        revoke(http_client, req, client_id).await?;
+        Ok(())
+    }
+}
+
+/// Get the request to revoke a token, see <https://dev.twitch.tv/docs/authentication/revoke-tokens/>
+fn revoke_request(
+    token: &str,
+    token_type_hint: Option<&str>,
+    client_id: &ClientId,
+) -> http::Request<Vec<u8>> {
+    use http::{HeaderMap, Method};
+    use std::collections::HashMap;
+    let mut params = HashMap::new();
+    params.insert("client_id", client_id.as_str());
+    params.insert("token", token);
+    if let Some(token_type_hint) = token_type_hint {
+        params.insert("token_type_hint", token_type_hint);
+    }
+
+    construct_request(
+        &crate::REVOKE_URL,
+        &params,
+        HeaderMap::new(),
+        Method::POST,
+        vec![],
+    )
+}
+
+/// Send a request to revoke a token, emitting the [revocation event](tokens::events::TokenEvents::revoked)
+#[cfg(feature = "client")]
+async fn revoke<C: Client>(
+    http_client: &C,
+    req: http::Request<Vec<u8>>,
+    client_id: &ClientId,
+) -> Result<(), RevokeTokenError<<C as Client>::Error>> {
+    let start = Instant::now();
+    let result = async {
+        let resp = trace::send(http_client, req)
             .await
             .map_err(RevokeTokenError::RequestError)?;
-
-        let _ = parse_token_response_raw(&resp)?;
+        parse_token_response_raw(&resp)?;
         Ok(())
     }
+    .await;
+    tokens::events::emit(http_client, |events| {
+        events.revoked(&tokens::events::RevocationEvent {
+            client_id,
+            elapsed: start.elapsed(),
+            outcome: result
+                .as_ref()
+                .copied()
+                .map_err(tokens::events::ErrorKind::revoke),
+        })
+    });
+    result
 }
 
 impl RefreshTokenRef {
+    /// Get the request needed to revoke this token.
+    pub fn revoke_token_request(&self, client_id: &ClientId) -> http::Request<Vec<u8>> {
+        revoke_request(self.secret(), Some("refresh_token"), client_id)
+    }
+
+    /// Revoke the refresh token, so it can't be used to get new access tokens.
+    ///
+    /// Revoking the access token doesn't revoke the refresh token, use [`TwitchToken::revoke_token_and_refresh_token`] to revoke both.
+    ///
+    /// See <https://dev.twitch.tv/docs/authentication/revoke-tokens/>
+    #[cfg(feature = "client")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "revoke_refresh_token",
+            skip_all,
+            fields(client_id = %client_id),
+            err(level = "warn", Display)
+        )
+    )]
+    pub async fn revoke_token<C>(
+        &self,
+        http_client: &C,
+        client_id: &ClientId,
+    ) -> Result<(), RevokeTokenError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        let req = self.revoke_token_request(client_id);
+
+        revoke(http_client, req, client_id).await
+    }
+
     /// Get the request needed to refresh this token.
     ///
     /// Parse the response from this endpoint with [TwitchTokenResponse::from_response](crate::id::TwitchTokenResponse::from_response)
@@ -270,12 +392,15 @@ impl RefreshTokenRef {
         }
         params.insert("grant_type", "refresh_token");
         params.insert("refresh_token", self.secret());
+        // sized up front so no partial copies of the secrets are left behind when growing
+        let body =
+            String::with_capacity(form_encoded_capacity(params.iter().map(|(k, v)| (*k, *v))));
         construct_request::<&[(String, String)], _, _>(
             &crate::TOKEN_URL,
             &[],
             headers,
             Method::POST,
-            url::form_urlencoded::Serializer::new(String::new())
+            url::form_urlencoded::Serializer::new(body)
                 .extend_pairs(params)
                 .finish()
                 .into_bytes(),
@@ -298,19 +423,71 @@ impl RefreshTokenRef {
     where
         C: Client,
     {
-        let req = self.refresh_token_request(client_id, client_secret);
-
-        let resp = http_client
-            .req(req)
-            .await
-            .map_err(RefreshTokenError::RequestError)?;
-        let res = id::TwitchTokenResponse::from_response(&resp)?;
+        let res = self
+            .refresh_token_response(http_client, client_id, client_secret)
+            .await?;
 
         let expires_in = res.expires_in().ok_or(RefreshTokenError::NoExpiration)?;
         let refresh_token = res.refresh_token;
         let access_token = res.access_token;
         Ok((access_token, expires_in, refresh_token))
     }
+
+    /// Refresh the token, returning the full response including the scopes.
+    #[cfg(feature = "client")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "refresh_token",
+            skip_all,
+            fields(client_id = %client_id),
+            err(level = "warn", Display)
+        )
+    )]
+    pub(crate) async fn refresh_token_response<C>(
+        &self,
+        http_client: &C,
+        client_id: &ClientId,
+        client_secret: Option<&ClientSecret>,
+    ) -> Result<id::TwitchTokenResponse, RefreshTokenError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        let req = self.refresh_token_request(client_id, client_secret);
+
+        let mut resp = trace::send(http_client, req)
+            .await
+            .map_err(RefreshTokenError::RequestError)?;
+        let response = id::TwitchTokenResponse::from_response(&resp);
+        wipe_bytes(resp.body_mut());
+        Ok(response?)
+    }
+}
+
+/// Upper bound of the length of form encoded pairs
+fn form_encoded_capacity<'a>(pairs: impl IntoIterator<Item = (&'a str, &'a str)>) -> usize {
+    // every byte is at most percent encoded to three bytes, plus `=` and `&`
+    pairs
+        .into_iter()
+        .map(|(k, v)| 3 * (k.len() + v.len()) + 2)
+        .sum()
+}
+
+/// Wipe a string that contained secrets from memory when the `zeroize` feature is enabled.
+fn wipe(s: &mut String) {
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(s);
+    #[cfg(not(feature = "zeroize"))]
+    let _ = s;
+}
+
+/// Wipe a buffer that contained secrets from memory when the `zeroize` feature is enabled.
+#[cfg(feature = "client")]
+fn wipe_bytes(b: &mut Vec<u8>) {
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(b);
+    #[cfg(not(feature = "zeroize"))]
+    let _ = b;
 }
 
 /// Construct a request that accepts `application/json` on default
@@ -327,9 +504,25 @@ where
     K: AsRef<str>,
     V: AsRef<str>,
 {
-    let mut url = url.clone();
-    url.query_pairs_mut().extend_pairs(params);
-    let url: String = url.into();
+    let params = params.into_iter().collect::<Vec<_>>();
+    // the query contains secrets, build the url in a buffer sized up front so no partial copies are left behind when growing
+    let capacity = form_encoded_capacity(params.iter().map(|p| {
+        let (k, v) = std::borrow::Borrow::borrow(p);
+        (k.as_ref(), v.as_ref())
+    }));
+    debug_assert!(url.fragment().is_none(), "url can't have a fragment");
+    let mut target = String::with_capacity(url.as_str().len() + 1 + capacity);
+    target.push_str(url.as_str());
+    let query_start = match url.query() {
+        Some(query) => target.len() - query.len(),
+        None => {
+            target.push('?');
+            target.len()
+        }
+    };
+    let url = url::form_urlencoded::Serializer::for_suffix(target, query_start)
+        .extend_pairs(params)
+        .finish();
     let mut req = http::Request::builder().method(method).uri(url);
     req.headers_mut().map(|h| h.extend(headers)).unwrap();
     req.headers_mut()
@@ -346,10 +539,18 @@ where
 pub(crate) fn parse_token_response_raw<B: AsRef<[u8]>>(
     resp: &http::Response<B>,
 ) -> Result<&http::Response<B>, RequestParseError> {
+    if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+        return Err(RequestParseError::RateLimited(
+            id::RateLimit::from_response(resp),
+        ));
+    }
     match serde_json::from_slice::<TwitchTokenErrorResponse>(resp.body().as_ref()) {
         Err(_) => match resp.status() {
             StatusCode::OK => Ok(resp),
-            _ => Err(RequestParseError::Other(resp.status())),
+            status => Err(RequestParseError::Other {
+                status,
+                response: Box::new(ResponseSnippet::new(resp)),
+            }),
         },
         Ok(twitch_err) => Err(RequestParseError::TwitchError(twitch_err)),
     }
@@ -366,10 +567,14 @@ pub(crate) fn parse_response<T: serde::de::DeserializeOwned, B: AsRef<[u8]>>(
         if _content != "application/json" {
             return Err(RequestParseError::NotJson {
                 found: String::from_utf8_lossy(_content.as_bytes()).into_owned(),
+                response: Box::new(ResponseSnippet::new(resp)),
             });
         }
     }
-    serde_json::from_slice(body).map_err(Into::into)
+    serde_json::from_slice(body).map_err(|error| RequestParseError::DeserializeError {
+        error,
+        response: Box::new(ResponseSnippet::new(resp)),
+    })
 }
 
 /// Errors from parsing responses
@@ -377,14 +582,280 @@ pub(crate) fn parse_response<T: serde::de::DeserializeOwned, B: AsRef<[u8]>>(
 #[non_exhaustive]
 pub enum RequestParseError {
     /// deserialization failed
-    DeserializeError(#[from] serde_json::Error),
+    DeserializeError {
+        /// Error deserializing the body
+        #[source]
+        error: serde_json::Error,
+        /// The response that could not be deserialized
+        response: Box<ResponseSnippet>,
+    },
     /// twitch returned an error
     TwitchError(#[from] TwitchTokenErrorResponse),
     /// returned content is not `application/json`, found `{found}`
     NotJson {
         /// Found `Content-Type` header
         found: String,
+        /// The response that was not json
+        response: Box<ResponseSnippet>,
     },
-    /// twitch returned an unexpected status code: {0}
-    Other(StatusCode),
+    /// twitch returned an unexpected status code: {status}
+    Other {
+        /// Status code of the response
+        status: StatusCode,
+        /// The response with the unexpected status code
+        response: Box<ResponseSnippet>,
+    },
+    /// twitch rate limited the request
+    RateLimited(Option<id::RateLimit>),
+}
+
+impl RequestParseError {
+    /// Get the response that could not be parsed, if it was retained
+    pub fn response(&self) -> Option<&ResponseSnippet> {
+        match self {
+            RequestParseError::DeserializeError { response, .. }
+            | RequestParseError::NotJson { response, .. }
+            | RequestParseError::Other { response, .. } => Some(response),
+            _ => None,
+        }
+    }
+
+    /// Get the rate limit if twitch rate limited the request, and returned the rate limit headers
+    pub fn rate_limit(&self) -> Option<&id::RateLimit> {
+        match self {
+            RequestParseError::RateLimited(rate_limit) => rate_limit.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// The part of a response retained in a [`RequestParseError`], to diagnose unexpected responses
+///
+/// The body is cut off after [`ResponseSnippet::MAX_BODY_LEN`] bytes and tokens, secrets and codes in it are redacted.
+/// Only headers useful for diagnosing are kept, see [`ResponseSnippet::HEADERS`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ResponseSnippet {
+    /// Status code of the response
+    pub status: StatusCode,
+    /// Headers of the response useful for diagnosing
+    pub headers: http::HeaderMap,
+    /// Start of the body, lossily decoded as UTF-8 and with secrets redacted
+    pub body: String,
+    /// Whether the body was cut off
+    pub truncated: bool,
+}
+
+impl ResponseSnippet {
+    /// Headers that are retained
+    pub const HEADERS: &'static [&'static str] = &[
+        "content-type",
+        "content-length",
+        "date",
+        "retry-after",
+        "ratelimit-limit",
+        "ratelimit-remaining",
+        "ratelimit-reset",
+    ];
+    /// Maximum length of the retained body, in bytes
+    pub const MAX_BODY_LEN: usize = 1024;
+    /// Keys of values that are redacted from the body
+    const SECRETS: &'static [&'static str] = &[
+        "access_token",
+        "refresh_token",
+        "id_token",
+        "client_secret",
+        "device_code",
+        "code",
+        "token",
+    ];
+
+    /// Retain the relevant parts of a response
+    pub fn new<B: AsRef<[u8]>>(response: &http::Response<B>) -> Self {
+        let mut headers = http::HeaderMap::new();
+        for name in Self::HEADERS {
+            for value in response.headers().get_all(*name) {
+                headers.append(*name, value.clone());
+            }
+        }
+        let body = response.body().as_ref();
+        let mut body = match serde_json::from_slice::<serde_json::Value>(body) {
+            Ok(mut json) => {
+                Self::redact_json(&mut json);
+                json.to_string()
+            }
+            Err(_) => Self::redact_text(&String::from_utf8_lossy(body)),
+        };
+        let truncated = body.len() > Self::MAX_BODY_LEN;
+        if truncated {
+            let mut end = Self::MAX_BODY_LEN;
+            while !body.is_char_boundary(end) {
+                end -= 1;
+            }
+            body.truncate(end);
+        }
+        Self {
+            status: response.status(),
+            headers,
+            body,
+            truncated,
+        }
+    }
+
+    /// Get the `Content-Type` of the response
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get(http::header::CONTENT_TYPE)?.to_str().ok()
+    }
+
+    fn redact_json(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, value) in map {
+                    if Self::SECRETS.contains(&key.as_str()) {
+                        *value = "[redacted]".into();
+                    } else {
+                        Self::redact_json(value);
+                    }
+                }
+            }
+            serde_json::Value::Array(values) => values.iter_mut().for_each(Self::redact_json),
+            _ => {}
+        }
+    }
+
+    /// Redact the values after the secret keys, like in `access_token=...` or `"access_token": "..."`
+    fn redact_text(text: &str) -> String {
+        let is_key = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        let mut redacted = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(|c: char| is_key(c)) {
+            redacted.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = rest.find(|c: char| !is_key(c)).unwrap_or(rest.len());
+            let (key, after) = rest.split_at(end);
+            redacted.push_str(key);
+            rest = after;
+            if !Self::SECRETS.contains(&key) {
+                continue;
+            }
+            let separator = rest
+                .find(|c: char| !matches!(c, '"' | '\'' | ':' | '=' | ' '))
+                .unwrap_or(rest.len());
+            if !rest[..separator].contains([':', '=']) {
+                continue;
+            }
+            redacted.push_str(&rest[..separator]);
+            rest = &rest[separator..];
+            let value = rest
+                .find(|c: char| {
+                    matches!(c, '"' | '\'' | '&' | ',' | '}' | ';') || c.is_whitespace()
+                })
+                .unwrap_or(rest.len());
+            redacted.push_str("[redacted]");
+            rest = &rest[value..];
+        }
+        redacted.push_str(rest);
+        redacted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn construct_request_query() {
+        let url = url::Url::parse("https://id.twitch.tv/oauth2/token").unwrap();
+        let req = construct_request(
+            &url,
+            &[("client_secret", "a&b c"), ("code", "1")],
+            http::HeaderMap::new(),
+            http::Method::POST,
+            vec![],
+        );
+        assert_eq!(
+            req.uri(),
+            "https://id.twitch.tv/oauth2/token?client_secret=a%26b+c&code=1"
+        );
+
+        let url = url::Url::parse("http://localhost:8080/auth/token?mock=1").unwrap();
+        let req = construct_request::<&[(String, String)], _, _>(
+            &url,
+            &[],
+            http::HeaderMap::new(),
+            http::Method::POST,
+            vec![],
+        );
+        assert_eq!(req.uri(), "http://localhost:8080/auth/token?mock=1");
+        let req = construct_request(
+            &url,
+            &[("token", "secret")],
+            http::HeaderMap::new(),
+            http::Method::POST,
+            vec![],
+        );
+        assert_eq!(
+            req.uri(),
+            "http://localhost:8080/auth/token?mock=1&token=secret"
+        );
+    }
+
+    #[test]
+    fn rate_limited() {
+        let response = http::Response::builder()
+            .status(429)
+            .header("Ratelimit-Limit", "800")
+            .header("Ratelimit-Remaining", "0")
+            .header("Ratelimit-Reset", "1700000000")
+            .body(br#"{"status":429,"message":"Too Many Requests"}"#.to_vec())
+            .unwrap();
+        let error = parse_token_response_raw(&response).unwrap_err();
+        let rate_limit = error.rate_limit().unwrap();
+        assert!(rate_limit.is_exhausted());
+        assert_eq!(rate_limit.limit, 800);
+        assert_eq!(
+            rate_limit.reset,
+            std::time::UNIX_EPOCH + std::time::Duration::from_secs(1700000000)
+        );
+    }
+
+    #[test]
+    fn response_snippet() {
+        let response = http::Response::builder()
+            .status(500)
+            .header("Content-Type", "application/json")
+            .header("Set-Cookie", "session=secret")
+            .body(
+                br#"{"access_token":"secrettoken","nested":[{"refresh_token":"secretrefresh"}],"expires_in":10}"#
+                    .to_vec(),
+            )
+            .unwrap();
+        let RequestParseError::Other { status, response } =
+            parse_token_response_raw(&response).unwrap_err()
+        else {
+            panic!("expected unexpected status");
+        };
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.content_type(), Some("application/json"));
+        assert!(response.headers.get("set-cookie").is_none());
+        assert!(!response.body.contains("secret"), "{}", response.body);
+        assert!(response.body.contains(r#""expires_in":10"#));
+
+        let response = http::Response::builder()
+            .header("Content-Type", "text/html")
+            .body(
+                "<p>access_token=secrettoken&scope=chat:read 'code': 'secretcode'</p>"
+                    .repeat(100)
+                    .into_bytes(),
+            )
+            .unwrap();
+        let error = parse_response::<id::TwitchTokenResponse, _>(&response).unwrap_err();
+        let snippet = error.response().unwrap();
+        assert!(snippet.truncated);
+        assert_eq!(snippet.body.len(), ResponseSnippet::MAX_BODY_LEN);
+        assert!(!snippet.body.contains("secret"), "{}", snippet.body);
+        assert!(snippet
+            .body
+            .starts_with("<p>access_token=[redacted]&scope=chat:read 'code': '[redacted]'</p>"));
+    }
 }
@@ -1,5 +1,11 @@
 //! Module for all possible scopes in twitch.
+pub(crate) mod deprecation;
+pub mod metadata;
+pub mod set;
 pub mod validator;
+pub use deprecation::migrate_scopes;
+pub use metadata::{ScopeAccess, ScopeCategory, ScopeMetadata};
+pub use set::{ScopeDiff, ScopeLookup, ScopeSet};
 pub use validator::Validator;
 
 use serde_derive::{Deserialize, Serialize};
@@ -13,8 +19,14 @@ macro_rules! scope_impls {
     (@omit $i:ident) => {
         Self::$i
     };
+    (@deprecated #[deprecated($depr:tt)]) => {
+        true
+    };
+    (@deprecated) => {
+        false
+    };
 
-    ($($(#[cfg(($cfg:meta))])* $(#[deprecated($depr:meta)])? $i:ident,scope: $rename:literal, doc: $doc:literal);* $(;)? ) => {
+    ($($(#[cfg(($cfg:meta))])* $(#[deprecated($depr:meta)])? $i:ident,scope: $rename:literal, doc: $doc:literal $(, replaced_by: [$($replacement:ident),* $(,)?])?);* $(;)? ) => {
         #[doc = "Scopes for twitch."]
         #[doc = ""]
         #[doc = "<https://dev.twitch.tv/docs/authentication/scopes/>"]
@@ -116,6 +128,32 @@ macro_rules! scope_impls {
                 }
             }
 
+            /// Returns true if the scope is deprecated, see [`Scope::replacements`]
+            pub const fn is_deprecated(&self) -> bool {
+                #![allow(deprecated)]
+                match self {
+                    $(
+                        $(#[cfg($cfg)])*
+                        Scope::$i => scope_impls!(@deprecated $(#[deprecated($depr)])*),
+                    )*
+                    Self::Other(_) => false,
+                }
+            }
+
+            /// Get the scopes replacing a deprecated scope.
+            ///
+            /// Returns an empty slice if the scope is not deprecated, or if it has no replacement.
+            pub const fn replacements(&self) -> &'static [Scope] {
+                #![allow(deprecated)]
+                match self {
+                    $(
+                        $(#[cfg($cfg)])*
+                        Scope::$i => &[$($(Scope::$replacement,)*)?],
+                    )*
+                    Self::Other(_) => &[],
+                }
+            }
+
             /// Get the scope as a static string slice.
             ///
             /// ## Panics
@@ -133,6 +171,50 @@ macro_rules! scope_impls {
                 }
             }
         }
+        /// Index of a known scope in a [`ScopeSet`]
+        #[repr(u8)]
+        #[allow(dead_code)]
+        enum ScopeIndex {
+            $(
+                $(#[cfg($cfg)])*
+                $i,
+            )*
+        }
+
+        impl Scope {
+            /// All known scopes, including deprecated ones, ordered by their index in a [`ScopeSet`]
+            #[allow(deprecated)]
+            pub(crate) const KNOWN: &'static [Scope] = &[
+                $(
+                    $(#[cfg($cfg)])*
+                    Scope::$i,
+                )*
+            ];
+
+            /// Get the index of a known scope in a [`ScopeSet`]
+            pub(crate) const fn index(&self) -> Option<u8> {
+                #![allow(deprecated)]
+                match self {
+                    $(
+                        $(#[cfg($cfg)])*
+                        Scope::$i => Some(ScopeIndex::$i as u8),
+                    )*
+                    Self::Other(_) => None,
+                }
+            }
+
+            /// Get the index of a known scope in a [`ScopeSet`] from its name
+            pub(crate) fn index_of(s: &str) -> Option<u8> {
+                match s {
+                    $(
+                        $(#[cfg($cfg)])*
+                        $rename => Some(ScopeIndex::$i as u8),
+                    )*
+                    _ => None,
+                }
+            }
+        }
+
         #[test]
         #[cfg(test)]
         fn sorted() {
@@ -281,11 +363,11 @@ scope_impls!(
     // Deprecated/removed scopes:
 
     #[deprecated(note = "Use `ChannelReadSubscriptions` (`channel:read:subscriptions`) instead")]
-    ChannelSubscriptions,           scope: "channel_subscriptions",             doc: "Read all subscribers to your channel.";
+    ChannelSubscriptions,           scope: "channel_subscriptions",             doc: "Read all subscribers to your channel.", replaced_by: [ChannelReadSubscriptions];
     #[deprecated(note = "Not used anymore, see https://discuss.dev.twitch.tv/t/deprecation-of-create-and-delete-follows-api-endpoints/32351")]
-    UserEditFollows,                scope: "user:edit:follows",                 doc: "\\[DEPRECATED\\] Was previously used for “Create User Follows” and “Delete User Follows.";
+    UserEditFollows,                scope: "user:edit:follows",                 doc: "\\[DEPRECATED\\] Was previously used for “Create User Follows” and “Delete User Follows.", replaced_by: [];
     #[deprecated(note = "Use `UserManageWhispers` (`user:manage:whispers`) instead")]
-    WhispersEdit,                   scope: "whispers:edit",                     doc: "\\[DEPRECATED\\] Send whisper messages.";
+    WhispersEdit,                   scope: "whispers:edit",                     doc: "\\[DEPRECATED\\] Send whisper messages.", replaced_by: [UserManageWhispers];
 );
 
 impl Scope {
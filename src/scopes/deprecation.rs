@@ -0,0 +1,112 @@
+//! Migration of deprecated [scopes](Scope).
+//!
+//! With the `tracing` feature, a warning is logged when a token is created with deprecated scopes.
+use twitch_types::UserIdRef;
+
+use super::{Scope, ScopeSet};
+use crate::ClientIdRef;
+
+impl Scope {
+    /// Get the current scopes to use instead of this scope.
+    ///
+    /// Returns the [replacements](Scope::replacements) if the scope is deprecated, otherwise the scope itself.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::Scope;
+    ///
+    /// # #[allow(deprecated)]
+    /// assert_eq!(Scope::WhispersEdit.migrate(), [Scope::UserManageWhispers]);
+    /// assert_eq!(Scope::ChatRead.migrate(), [Scope::ChatRead]);
+    /// ```
+    pub fn migrate(self) -> Vec<Scope> {
+        if self.is_deprecated() {
+            self.replacements().to_vec()
+        } else {
+            vec![self]
+        }
+    }
+}
+
+/// Rewrite a list of scopes to current scopes, replacing deprecated scopes with their [replacements](Scope::replacements).
+///
+/// The order of the scopes is kept and duplicates are removed.
+///
+/// # Examples
+///
+/// ```rust
+/// use twitch_oauth2::{scopes::migrate_scopes, Scope};
+///
+/// let stored = vec![
+///     "channel_subscriptions",
+///     "chat:read",
+///     "channel:read:subscriptions",
+/// ];
+/// assert_eq!(
+///     migrate_scopes(stored.into_iter().map(Scope::parse)),
+///     [Scope::ChannelReadSubscriptions, Scope::ChatRead]
+/// );
+/// ```
+pub fn migrate_scopes(scopes: impl IntoIterator<Item = Scope>) -> Vec<Scope> {
+    let mut seen = ScopeSet::new();
+    scopes
+        .into_iter()
+        .flat_map(Scope::migrate)
+        .filter(|scope| seen.insert(scope.clone()))
+        .collect()
+}
+
+/// Warn that a token was issued with deprecated scopes, no-op unless the `tracing` feature is enabled.
+pub(crate) fn warn_issued(client_id: &ClientIdRef, user_id: Option<&UserIdRef>, scopes: &ScopeSet) {
+    #[cfg(feature = "tracing")]
+    {
+        let deprecated = scopes.deprecated();
+        if !deprecated.is_empty() {
+            let replacements: ScopeSet = migrate_scopes(deprecated.clone()).into_iter().collect();
+            tracing::warn!(
+                client_id = client_id.as_str(),
+                user_id = user_id.map(|id| id.as_str()),
+                %deprecated,
+                %replacements,
+                "token was issued with deprecated scopes"
+            );
+        }
+    }
+    #[cfg(not(feature = "tracing"))]
+    let _ = (client_id, user_id, scopes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_have_replacements() {
+        for scope in Scope::KNOWN {
+            for replacement in scope.replacements() {
+                assert!(scope.is_deprecated(), "{scope}");
+                assert!(!replacement.is_deprecated(), "{scope} -> {replacement}");
+            }
+        }
+        assert!(Scope::UserEditFollows.is_deprecated());
+        assert_eq!(Scope::UserEditFollows.migrate(), []);
+        assert!(!Scope::Other("whispers:edit".into()).is_deprecated());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn diff_reports_deprecated() {
+        let old = ScopeSet::from([Scope::ChatRead]);
+        let new = ScopeSet::from([Scope::ChatRead, Scope::WhispersEdit]);
+        let diff = old.diff(&new);
+        assert_eq!(diff.deprecated, [Scope::WhispersEdit].into());
+        assert!(new.diff(&new).deprecated.contains(&Scope::WhispersEdit));
+        assert!(new.diff(&old).deprecated.is_empty());
+        assert_eq!(
+            migrate_scopes(new.deprecated()),
+            [Scope::UserManageWhispers]
+        );
+    }
+}
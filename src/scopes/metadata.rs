@@ -0,0 +1,409 @@
+//! Structured information about scopes.
+//!
+//! See [`Scope::metadata`]
+use super::Scope;
+
+/// Category of a [`Scope`], derived from the first part of the scope name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ScopeCategory {
+    /// `analytics:*` scopes
+    Analytics,
+    /// `bits:*` scopes
+    Bits,
+    /// `channel:*` scopes
+    Channel,
+    /// `chat:*` scopes, used for IRC
+    Chat,
+    /// `clips:*` scopes
+    Clips,
+    /// `editor:*` scopes
+    Editor,
+    /// `moderation:*` scopes
+    Moderation,
+    /// `moderator:*` scopes
+    Moderator,
+    /// `user:*` scopes
+    User,
+    /// `whispers:*` scopes, used for IRC and PubSub
+    Whispers,
+}
+
+/// Whether a [`Scope`] grants access to read or to modify data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScopeAccess {
+    /// The scope only grants access to view data
+    Read,
+    /// The scope grants access to perform actions or change data
+    Write,
+}
+
+/// Metadata for a [`Scope`]
+///
+/// Retrieve with [`Scope::metadata`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct ScopeMetadata {
+    /// Category of the scope
+    pub category: ScopeCategory,
+    /// Whether the scope grants read or write access
+    pub access: ScopeAccess,
+    /// The scope grants access to private information, like the email address, stream key or whispers of the user.
+    pub sensitive: bool,
+    /// [EventSub subscription types](https://dev.twitch.tv/docs/eventsub/eventsub-subscription-types/) that can require this scope
+    pub eventsub: &'static [&'static str],
+    /// [Helix endpoints](https://dev.twitch.tv/docs/api/reference/) that can require this scope, named as in the reference
+    pub endpoints: &'static [&'static str],
+}
+
+impl ScopeMetadata {
+    /// Returns true if the scope grants read access only
+    pub const fn is_read(&self) -> bool { matches!(self.access, ScopeAccess::Read) }
+
+    /// Returns true if any EventSub subscription type can require this scope
+    pub const fn is_eventsub(&self) -> bool { !self.eventsub.is_empty() }
+}
+
+macro_rules! meta {
+    ($category:ident, $access:ident $(, $sensitive:ident)?; eventsub: [$($eventsub:literal),* $(,)?], endpoints: [$($endpoint:literal),* $(,)?] $(,)?) => {{
+        const META: ScopeMetadata = ScopeMetadata {
+            category: ScopeCategory::$category,
+            access: ScopeAccess::$access,
+            sensitive: meta!(@sensitive $($sensitive)?),
+            eventsub: &[$($eventsub),*],
+            endpoints: &[$($endpoint),*],
+        };
+        Some(&META)
+    }};
+    (@sensitive sensitive) => { true };
+    (@sensitive) => { false };
+}
+
+const CHAT_EVENTSUB: &[&str] = &[
+    "channel.chat.clear",
+    "channel.chat.clear_user_messages",
+    "channel.chat.message",
+    "channel.chat.message_delete",
+    "channel.chat.notification",
+    "channel.chat_settings.update",
+    "channel.chat.user_message_hold",
+    "channel.chat.user_message_update",
+];
+
+const GUEST_STAR_EVENTSUB: &[&str] = &[
+    "channel.guest_star_session.begin",
+    "channel.guest_star_session.end",
+    "channel.guest_star_guest.update",
+    "channel.guest_star_settings.update",
+];
+
+const GUEST_STAR_READ_ENDPOINTS: &[&str] = &[
+    "Get Channel Guest Star Settings",
+    "Get Guest Star Session",
+    "Get Guest Star Invites",
+];
+
+const GUEST_STAR_MANAGE_ENDPOINTS: &[&str] = &[
+    "Get Channel Guest Star Settings",
+    "Update Channel Guest Star Settings",
+    "Get Guest Star Session",
+    "Create Guest Star Session",
+    "End Guest Star Session",
+    "Get Guest Star Invites",
+    "Send Guest Star Invite",
+    "Delete Guest Star Invite",
+    "Assign Guest Star Slot",
+    "Update Guest Star Slot",
+    "Delete Guest Star Slot",
+    "Update Guest Star Slot Settings",
+];
+
+impl Scope {
+    /// Get the [metadata](ScopeMetadata) of this scope
+    ///
+    /// Returns `None` for [`Scope::Other`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::{scopes::ScopeCategory, Scope};
+    ///
+    /// const META: &twitch_oauth2::scopes::ScopeMetadata = match Scope::UserReadEmail.metadata() {
+    ///     Some(meta) => meta,
+    ///     None => panic!(),
+    /// };
+    /// assert_eq!(META.category, ScopeCategory::User);
+    /// assert!(META.is_read());
+    /// assert!(META.sensitive);
+    /// assert!(META.endpoints.contains(&"Get Users"));
+    /// ```
+    #[rustfmt::skip]
+    pub const fn metadata(&self) -> Option<&'static ScopeMetadata> {
+        #![allow(deprecated)]
+        match self {
+            Scope::AnalyticsReadExtensions => meta!(Analytics, Read; eventsub: [], endpoints: ["Get Extension Analytics"]),
+            Scope::AnalyticsReadGames => meta!(Analytics, Read; eventsub: [], endpoints: ["Get Game Analytics"]),
+            Scope::BitsRead => meta!(Bits, Read; eventsub: ["channel.bits.use", "channel.cheer"], endpoints: ["Get Bits Leaderboard"]),
+            Scope::ChannelBot => meta!(Channel, Write; eventsub: [
+                "channel.chat.clear",
+                "channel.chat.clear_user_messages",
+                "channel.chat.message",
+                "channel.chat.message_delete",
+                "channel.chat.notification",
+                "channel.chat_settings.update",
+            ], endpoints: ["Send Chat Message"]),
+            Scope::ChannelEditCommercial => meta!(Channel, Write; eventsub: [], endpoints: ["Start Commercial"]),
+            Scope::ChannelManageAds => meta!(Channel, Write; eventsub: [], endpoints: ["Get Ad Schedule", "Snooze Next Ad"]),
+            Scope::ChannelManageBroadcast => meta!(Channel, Write; eventsub: [], endpoints: ["Modify Channel Information", "Create Stream Marker"]),
+            Scope::ChannelManageClips => meta!(Channel, Write; eventsub: [], endpoints: []),
+            Scope::ChannelManageExtensions => meta!(Channel, Write; eventsub: [], endpoints: []),
+            Scope::ChannelManageGuestStar => Some(&ScopeMetadata {
+                category: ScopeCategory::Channel,
+                access: ScopeAccess::Write,
+                sensitive: false,
+                eventsub: GUEST_STAR_EVENTSUB,
+                endpoints: GUEST_STAR_MANAGE_ENDPOINTS,
+            }),
+            Scope::ChannelManageModerators => meta!(Channel, Write; eventsub: ["channel.moderator.add", "channel.moderator.remove"], endpoints: ["Get Moderators", "Add Channel Moderator", "Remove Channel Moderator"]),
+            Scope::ChannelManagePolls => meta!(Channel, Write; eventsub: ["channel.poll.begin", "channel.poll.progress", "channel.poll.end"], endpoints: ["Get Polls", "Create Poll", "End Poll"]),
+            Scope::ChannelManagePredictions => meta!(Channel, Write; eventsub: [
+                "channel.prediction.begin",
+                "channel.prediction.progress",
+                "channel.prediction.lock",
+                "channel.prediction.end",
+            ], endpoints: ["Get Predictions", "Create Prediction", "End Prediction"]),
+            Scope::ChannelManageRaids => meta!(Channel, Write; eventsub: [], endpoints: ["Start a raid", "Cancel a raid"]),
+            Scope::ChannelManageRedemptions => meta!(Channel, Write; eventsub: [
+                "channel.channel_points_automatic_reward_redemption.add",
+                "channel.channel_points_custom_reward.add",
+                "channel.channel_points_custom_reward.update",
+                "channel.channel_points_custom_reward.remove",
+                "channel.channel_points_custom_reward_redemption.add",
+                "channel.channel_points_custom_reward_redemption.update",
+            ], endpoints: [
+                "Create Custom Rewards",
+                "Delete Custom Reward",
+                "Get Custom Reward",
+                "Get Custom Reward Redemption",
+                "Update Custom Reward",
+                "Update Redemption Status",
+            ]),
+            Scope::ChannelManageSchedule => meta!(Channel, Write; eventsub: [], endpoints: [
+                "Update Channel Stream Schedule",
+                "Create Channel Stream Schedule Segment",
+                "Update Channel Stream Schedule Segment",
+                "Delete Channel Stream Schedule Segment",
+            ]),
+            Scope::ChannelManageVideos => meta!(Channel, Write; eventsub: [], endpoints: ["Delete Videos"]),
+            Scope::ChannelManageVips => meta!(Channel, Write; eventsub: ["channel.vip.add", "channel.vip.remove"], endpoints: ["Get VIPs", "Add Channel VIP", "Remove Channel VIP"]),
+            Scope::ChannelModerate => meta!(Channel, Write; eventsub: ["channel.moderate"], endpoints: []),
+            Scope::ChannelReadAds => meta!(Channel, Read; eventsub: ["channel.ad_break.begin"], endpoints: ["Get Ad Schedule"]),
+            Scope::ChannelReadCharity => meta!(Channel, Read; eventsub: [
+                "channel.charity_campaign.donate",
+                "channel.charity_campaign.start",
+                "channel.charity_campaign.progress",
+                "channel.charity_campaign.stop",
+            ], endpoints: ["Get Charity Campaign", "Get Charity Campaign Donations"]),
+            Scope::ChannelReadEditors => meta!(Channel, Read; eventsub: [], endpoints: ["Get Channel Editors"]),
+            Scope::ChannelReadGoals => meta!(Channel, Read; eventsub: ["channel.goal.begin", "channel.goal.progress", "channel.goal.end"], endpoints: ["Get Creator Goals"]),
+            Scope::ChannelReadGuestStar => Some(&ScopeMetadata {
+                category: ScopeCategory::Channel,
+                access: ScopeAccess::Read,
+                sensitive: false,
+                eventsub: GUEST_STAR_EVENTSUB,
+                endpoints: GUEST_STAR_READ_ENDPOINTS,
+            }),
+            Scope::ChannelReadHypeTrain => meta!(Channel, Read; eventsub: ["channel.hype_train.begin", "channel.hype_train.progress", "channel.hype_train.end"], endpoints: ["Get Hype Train Events", "Get Hype Train Status"]),
+            Scope::ChannelReadPolls => meta!(Channel, Read; eventsub: ["channel.poll.begin", "channel.poll.progress", "channel.poll.end"], endpoints: ["Get Polls"]),
+            Scope::ChannelReadPredictions => meta!(Channel, Read; eventsub: [
+                "channel.prediction.begin",
+                "channel.prediction.progress",
+                "channel.prediction.lock",
+                "channel.prediction.end",
+            ], endpoints: ["Get Predictions"]),
+            Scope::ChannelReadRedemptions => meta!(Channel, Read; eventsub: [
+                "channel.channel_points_automatic_reward_redemption.add",
+                "channel.channel_points_custom_reward.add",
+                "channel.channel_points_custom_reward.update",
+                "channel.channel_points_custom_reward.remove",
+                "channel.channel_points_custom_reward_redemption.add",
+                "channel.channel_points_custom_reward_redemption.update",
+            ], endpoints: ["Get Custom Reward", "Get Custom Reward Redemption"]),
+            Scope::ChannelReadStreamKey => meta!(Channel, Read, sensitive; eventsub: [], endpoints: ["Get Stream Key"]),
+            Scope::ChannelReadSubscriptions => meta!(Channel, Read; eventsub: [
+                "channel.subscribe",
+                "channel.subscription.end",
+                "channel.subscription.gift",
+                "channel.subscription.message",
+            ], endpoints: ["Get Broadcaster Subscriptions"]),
+            Scope::ChannelReadVips => meta!(Channel, Read; eventsub: ["channel.vip.add", "channel.vip.remove"], endpoints: ["Get VIPs"]),
+            Scope::ChatEdit => meta!(Chat, Write; eventsub: [], endpoints: []),
+            Scope::ChatRead => meta!(Chat, Read; eventsub: [], endpoints: []),
+            Scope::ClipsEdit => meta!(Clips, Write; eventsub: [], endpoints: ["Create Clip"]),
+            Scope::EditorManageClips => meta!(Editor, Write; eventsub: [], endpoints: []),
+            Scope::ModerationRead => meta!(Moderation, Read; eventsub: ["channel.moderator.add", "channel.moderator.remove"], endpoints: ["Check AutoMod Status", "Get Banned Users", "Get Moderators"]),
+            Scope::ModeratorManageAnnouncements => meta!(Moderator, Write; eventsub: [], endpoints: ["Send Chat Announcement"]),
+            Scope::ModeratorManageAutoMod => meta!(Moderator, Write; eventsub: ["automod.message.hold", "automod.message.update"], endpoints: ["Manage Held AutoMod Messages"]),
+            Scope::ModeratorManageAutomodSettings => meta!(Moderator, Write; eventsub: ["automod.settings.update"], endpoints: ["Get AutoMod Settings", "Update AutoMod Settings"]),
+            Scope::ModeratorManageBannedUsers => meta!(Moderator, Write; eventsub: ["channel.moderate"], endpoints: ["Ban User", "Unban User"]),
+            Scope::ModeratorManageBlockedTerms => meta!(Moderator, Write; eventsub: ["automod.terms.update", "channel.moderate"], endpoints: ["Get Blocked Terms", "Add Blocked Term", "Remove Blocked Term"]),
+            Scope::ModeratorManageChatMessages => meta!(Moderator, Write; eventsub: ["channel.moderate"], endpoints: ["Delete Chat Messages"]),
+            Scope::ModeratorManageChatSettings => meta!(Moderator, Write; eventsub: ["channel.moderate"], endpoints: ["Update Chat Settings"]),
+            Scope::ModeratorManageGuestStar => Some(&ScopeMetadata {
+                category: ScopeCategory::Moderator,
+                access: ScopeAccess::Write,
+                sensitive: false,
+                eventsub: GUEST_STAR_EVENTSUB,
+                endpoints: GUEST_STAR_MANAGE_ENDPOINTS,
+            }),
+            Scope::ModeratorManageShieldMode => meta!(Moderator, Write; eventsub: ["channel.shield_mode.begin", "channel.shield_mode.end"], endpoints: ["Get Shield Mode Status", "Update Shield Mode Status"]),
+            Scope::ModeratorManageShoutouts => meta!(Moderator, Write; eventsub: ["channel.shoutout.create", "channel.shoutout.receive"], endpoints: ["Send a Shoutout"]),
+            Scope::ModeratorManageSuspiciousUsers => meta!(Moderator, Write; eventsub: [], endpoints: ["Add Suspicious Status to Chat User", "Remove Suspicious Status From Chat User"]),
+            Scope::ModeratorManageUnbanRequests => meta!(Moderator, Write; eventsub: ["channel.moderate", "channel.unban_request.create", "channel.unban_request.resolve"], endpoints: ["Get Unban Requests", "Resolve Unban Requests"]),
+            Scope::ModeratorManageWarnings => meta!(Moderator, Write; eventsub: ["channel.moderate", "channel.warning.acknowledge", "channel.warning.send"], endpoints: ["Warn Chat User"]),
+            Scope::ModeratorReadAutomodSettings => meta!(Moderator, Read; eventsub: ["automod.settings.update"], endpoints: ["Get AutoMod Settings"]),
+            Scope::ModeratorReadBannedUsers => meta!(Moderator, Read; eventsub: ["channel.moderate"], endpoints: []),
+            Scope::ModeratorReadBlockedTerms => meta!(Moderator, Read; eventsub: ["automod.terms.update", "channel.moderate"], endpoints: ["Get Blocked Terms"]),
+            Scope::ModeratorReadChatMessages => meta!(Moderator, Read; eventsub: ["channel.moderate"], endpoints: []),
+            Scope::ModeratorReadChatSettings => meta!(Moderator, Read; eventsub: ["channel.moderate"], endpoints: ["Get Chat Settings"]),
+            Scope::ModeratorReadChatters => meta!(Moderator, Read; eventsub: [], endpoints: ["Get Chatters"]),
+            Scope::ModeratorReadFollowers => meta!(Moderator, Read; eventsub: ["channel.follow"], endpoints: ["Get Channel Followers"]),
+            Scope::ModeratorReadGuestStar => Some(&ScopeMetadata {
+                category: ScopeCategory::Moderator,
+                access: ScopeAccess::Read,
+                sensitive: false,
+                eventsub: GUEST_STAR_EVENTSUB,
+                endpoints: GUEST_STAR_READ_ENDPOINTS,
+            }),
+            Scope::ModeratorReadModerators => meta!(Moderator, Read; eventsub: ["channel.moderate"], endpoints: []),
+            Scope::ModeratorReadShieldMode => meta!(Moderator, Read; eventsub: ["channel.shield_mode.begin", "channel.shield_mode.end"], endpoints: ["Get Shield Mode Status"]),
+            Scope::ModeratorReadShoutouts => meta!(Moderator, Read; eventsub: ["channel.shoutout.create", "channel.shoutout.receive"], endpoints: []),
+            Scope::ModeratorReadSuspiciousUsers => meta!(Moderator, Read; eventsub: ["channel.suspicious_user.message", "channel.suspicious_user.update"], endpoints: []),
+            Scope::ModeratorReadUnbanRequests => meta!(Moderator, Read; eventsub: ["channel.moderate", "channel.unban_request.create", "channel.unban_request.resolve"], endpoints: ["Get Unban Requests"]),
+            Scope::ModeratorReadVips => meta!(Moderator, Read; eventsub: ["channel.moderate"], endpoints: []),
+            Scope::ModeratorReadWarnings => meta!(Moderator, Read; eventsub: ["channel.moderate", "channel.warning.acknowledge", "channel.warning.send"], endpoints: []),
+            Scope::UserBot => Some(&ScopeMetadata {
+                category: ScopeCategory::User,
+                access: ScopeAccess::Write,
+                sensitive: false,
+                eventsub: CHAT_EVENTSUB,
+                endpoints: &["Send Chat Message"],
+            }),
+            Scope::UserEdit => meta!(User, Write; eventsub: [], endpoints: ["Update User"]),
+            Scope::UserEditBroadcast => meta!(User, Write; eventsub: [], endpoints: ["Update User Extensions"]),
+            Scope::UserManageBlockedUsers => meta!(User, Write; eventsub: [], endpoints: ["Block User", "Unblock User"]),
+            Scope::UserManageChatColor => meta!(User, Write; eventsub: [], endpoints: ["Update User Chat Color"]),
+            Scope::UserManageWhispers => meta!(User, Write, sensitive; eventsub: ["user.whisper.message"], endpoints: ["Send Whisper"]),
+            Scope::UserReadBlockedUsers => meta!(User, Read; eventsub: [], endpoints: ["Get User Block List"]),
+            Scope::UserReadBroadcast => meta!(User, Read; eventsub: [], endpoints: ["Get Stream Markers", "Get User Extensions", "Get User Active Extensions"]),
+            Scope::UserReadChat => Some(&ScopeMetadata {
+                category: ScopeCategory::User,
+                access: ScopeAccess::Read,
+                sensitive: false,
+                eventsub: CHAT_EVENTSUB,
+                endpoints: &[],
+            }),
+            Scope::UserReadEmail => meta!(User, Read, sensitive; eventsub: ["user.update"], endpoints: ["Get Users", "Update User"]),
+            Scope::UserReadEmotes => meta!(User, Read; eventsub: [], endpoints: ["Get User Emotes"]),
+            Scope::UserReadFollows => meta!(User, Read; eventsub: [], endpoints: ["Get Followed Streams", "Get Followed Channels"]),
+            Scope::UserReadModeratedChannels => meta!(User, Read; eventsub: [], endpoints: ["Get Moderated Channels"]),
+            Scope::UserReadSubscriptions => meta!(User, Read; eventsub: [], endpoints: ["Check User Subscription"]),
+            Scope::UserReadWhispers => meta!(User, Read, sensitive; eventsub: ["user.whisper.message"], endpoints: []),
+            Scope::UserWriteChat => meta!(User, Write; eventsub: [], endpoints: ["Send Chat Message"]),
+            Scope::WhispersRead => meta!(Whispers, Read, sensitive; eventsub: [], endpoints: []),
+            Scope::ChannelSubscriptions => meta!(Channel, Read; eventsub: [], endpoints: []),
+            Scope::UserEditFollows => meta!(User, Write; eventsub: [], endpoints: []),
+            Scope::WhispersEdit => meta!(Whispers, Write, sensitive; eventsub: [], endpoints: []),
+            Scope::Other(_) => None,
+        }
+    }
+
+    /// Get the [category](ScopeCategory) of this scope, `None` for [`Scope::Other`]
+    pub const fn category(&self) -> Option<ScopeCategory> {
+        match self.metadata() {
+            Some(meta) => Some(meta.category),
+            None => None,
+        }
+    }
+
+    /// Returns true if this scope grants access to private information, see [`ScopeMetadata::sensitive`]
+    ///
+    /// [`Scope::Other`] is never considered sensitive.
+    pub const fn is_sensitive(&self) -> bool {
+        match self.metadata() {
+            Some(meta) => meta.sensitive,
+            None => false,
+        }
+    }
+
+    /// Get all defined scopes that can be required by the given [Helix endpoint](https://dev.twitch.tv/docs/api/reference/)
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::Scope;
+    ///
+    /// let scopes: Vec<_> = Scope::required_by_endpoint("Send Chat Message").collect();
+    /// assert!(scopes.contains(&&Scope::UserWriteChat));
+    /// ```
+    pub fn required_by_endpoint(endpoint: &str) -> impl Iterator<Item = &'static Scope> + '_ {
+        Scope::all_slice().iter().filter(move |scope| {
+            scope
+                .metadata()
+                .is_some_and(|meta| meta.endpoints.contains(&endpoint))
+        })
+    }
+
+    /// Get all defined scopes that can be required by the given [EventSub subscription type](https://dev.twitch.tv/docs/eventsub/eventsub-subscription-types/)
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::Scope;
+    ///
+    /// let scopes: Vec<_> = Scope::required_by_eventsub("channel.follow").collect();
+    /// assert_eq!(scopes, [&Scope::ModeratorReadFollowers]);
+    /// ```
+    pub fn required_by_eventsub(
+        subscription_type: &str,
+    ) -> impl Iterator<Item = &'static Scope> + '_ {
+        Scope::all_slice().iter().filter(move |scope| {
+            scope
+                .metadata()
+                .is_some_and(|meta| meta.eventsub.contains(&subscription_type))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_scopes_have_metadata() {
+        for scope in Scope::all() {
+            let meta = scope.metadata().unwrap();
+            let (prefix, rest) = scope.as_str().split_once(':').unwrap();
+            let category = match prefix {
+                "analytics" => ScopeCategory::Analytics,
+                "bits" => ScopeCategory::Bits,
+                "channel" => ScopeCategory::Channel,
+                "chat" => ScopeCategory::Chat,
+                "clips" => ScopeCategory::Clips,
+                "editor" => ScopeCategory::Editor,
+                "moderation" => ScopeCategory::Moderation,
+                "moderator" => ScopeCategory::Moderator,
+                "user" => ScopeCategory::User,
+                "whispers" => ScopeCategory::Whispers,
+                _ => panic!("unknown category for {scope}"),
+            };
+            assert_eq!(meta.category, category, "{scope}");
+            assert_eq!(
+                meta.is_read(),
+                rest == "read" || rest.starts_with("read:"),
+                "{scope}"
+            );
+        }
+        assert_eq!(Scope::parse("custom:scope").metadata(), None);
+    }
+}
@@ -0,0 +1,579 @@
+//! A compact set of [scopes](Scope).
+use std::borrow::Cow;
+
+use super::Scope;
+
+const _: () = assert!(
+    Scope::KNOWN.len() <= u128::BITS as usize,
+    "too many scopes for a ScopeSet"
+);
+
+/// A set of [scopes](Scope).
+///
+/// Known scopes are stored in a bitset, [other scopes](Scope::Other) in a small sorted vec,
+/// making lookups and set operations cheap.
+///
+/// Iteration is in canonical order, known scopes in the order they are defined in [`Scope`], followed by other scopes sorted by name.
+///
+/// The [`Display`](std::fmt::Display) implementation and serialization use a space separated string, e.g. `chat:edit chat:read`.
+/// Deserialization accepts both a space separated string and a sequence of scopes.
+///
+/// # Examples
+///
+/// ```rust
+/// use twitch_oauth2::{Scope, ScopeSet};
+///
+/// let chat: ScopeSet = [Scope::ChatRead, Scope::ChatEdit].into_iter().collect();
+/// let user: ScopeSet = "user:read:chat chat:read".parse().unwrap();
+///
+/// assert_eq!(
+///     chat.union(&user).to_string(),
+///     "chat:edit chat:read user:read:chat"
+/// );
+/// assert_eq!(chat.intersection(&user).to_string(), "chat:read");
+/// assert_eq!(&chat - &user, ScopeSet::from([Scope::ChatEdit]));
+/// assert!(chat.contains(&Scope::ChatEdit));
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct ScopeSet {
+    known: u128,
+    /// Sorted by name, never contains known scopes
+    other: Vec<Scope>,
+}
+
+impl ScopeSet {
+    /// Create an empty set
+    pub const fn new() -> Self {
+        Self {
+            known: 0,
+            other: Vec::new(),
+        }
+    }
+
+    /// Create a set of all defined twitch [scopes](Scope), see [`Scope::all`]
+    pub fn all() -> Self { Scope::all_slice().iter().cloned().collect() }
+
+    /// Returns the number of scopes in the set
+    pub fn len(&self) -> usize { self.known.count_ones() as usize + self.other.len() }
+
+    /// Returns true if the set contains no scopes
+    pub fn is_empty(&self) -> bool { self.known == 0 && self.other.is_empty() }
+
+    /// Returns true if the set contains the scope
+    pub fn contains(&self, scope: &Scope) -> bool {
+        match known_index(scope) {
+            Some(index) => self.known & (1 << index) != 0,
+            None => self.find_other(scope.as_str()).is_ok(),
+        }
+    }
+
+    /// Add a scope to the set, returns true if it was not present
+    pub fn insert(&mut self, scope: Scope) -> bool {
+        if let Some(index) = known_index(&scope) {
+            let present = self.known & (1 << index) != 0;
+            self.known |= 1 << index;
+            return !present;
+        }
+        match self.find_other(scope.as_str()) {
+            Ok(_) => false,
+            Err(position) => {
+                self.other.insert(position, scope);
+                true
+            }
+        }
+    }
+
+    /// Remove a scope from the set, returns true if it was present
+    pub fn remove(&mut self, scope: &Scope) -> bool {
+        if let Some(index) = known_index(scope) {
+            let present = self.known & (1 << index) != 0;
+            self.known &= !(1 << index);
+            return present;
+        }
+        match self.find_other(scope.as_str()) {
+            Ok(position) => {
+                self.other.remove(position);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Remove all scopes from the set
+    pub fn clear(&mut self) {
+        self.known = 0;
+        self.other.clear();
+    }
+
+    /// Iterate over the scopes in canonical order
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            known: self.known,
+            other: self.other.iter(),
+        }
+    }
+
+    /// Returns the scopes in either set
+    pub fn union(&self, other: &ScopeSet) -> ScopeSet {
+        let mut set = self.clone();
+        for scope in &other.other {
+            set.insert(scope.clone());
+        }
+        set.known |= other.known;
+        set
+    }
+
+    /// Returns the scopes in both sets
+    pub fn intersection(&self, other: &ScopeSet) -> ScopeSet {
+        ScopeSet {
+            known: self.known & other.known,
+            other: self
+                .other
+                .iter()
+                .filter(|s| other.contains(s))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Returns the scopes in this set that are not in `other`
+    pub fn difference(&self, other: &ScopeSet) -> ScopeSet {
+        ScopeSet {
+            known: self.known & !other.known,
+            other: self
+                .other
+                .iter()
+                .filter(|s| !other.contains(s))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Returns the scopes in exactly one of the sets
+    pub fn symmetric_difference(&self, other: &ScopeSet) -> ScopeSet {
+        self.difference(other).union(&other.difference(self))
+    }
+
+    /// Returns true if all scopes in this set are in `other`
+    pub fn is_subset(&self, other: &ScopeSet) -> bool {
+        self.known & !other.known == 0 && self.other.iter().all(|s| other.contains(s))
+    }
+
+    /// Returns true if all scopes in `other` are in this set
+    pub fn is_superset(&self, other: &ScopeSet) -> bool { other.is_subset(self) }
+
+    /// Returns true if the sets have no scopes in common
+    pub fn is_disjoint(&self, other: &ScopeSet) -> bool {
+        self.known & other.known == 0 && !self.other.iter().any(|s| other.contains(s))
+    }
+
+    /// Returns the [deprecated](Scope::is_deprecated) scopes in this set, see [`migrate_scopes`](super::migrate_scopes) to replace them
+    pub fn deprecated(&self) -> ScopeSet {
+        self.iter().filter(|s| s.is_deprecated()).cloned().collect()
+    }
+
+    /// Get the scopes as a vec in canonical order
+    pub fn to_vec(&self) -> Vec<Scope> { self.iter().cloned().collect() }
+
+    /// Get the changes needed to go from this set to `new`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::{Scope, ScopeSet};
+    ///
+    /// let old = ScopeSet::from([Scope::ChatEdit, Scope::ChatRead]);
+    /// let new = ScopeSet::from([Scope::ChatRead, Scope::UserReadChat]);
+    /// let diff = old.diff(&new);
+    /// assert_eq!(diff.added, ScopeSet::from([Scope::UserReadChat]));
+    /// assert_eq!(diff.removed, ScopeSet::from([Scope::ChatEdit]));
+    /// assert_eq!(diff.to_string(), "+user:read:chat -chat:edit");
+    /// ```
+    pub fn diff(&self, new: &ScopeSet) -> ScopeDiff {
+        ScopeDiff {
+            added: new.difference(self),
+            removed: self.difference(new),
+            deprecated: new.deprecated(),
+        }
+    }
+
+    fn find_other(&self, scope: &str) -> Result<usize, usize> {
+        self.other.binary_search_by(|s| s.as_str().cmp(scope))
+    }
+}
+
+/// Changes to a set of scopes, see [`ScopeSet::diff`]
+///
+/// Returned when the scopes of a token are updated, e.g. by [`TwitchToken::refresh_token`](crate::TwitchToken::refresh_token).
+///
+/// The [`Display`](std::fmt::Display) implementation prefixes added scopes with `+` and removed scopes with `-`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct ScopeDiff {
+    /// Scopes that were added
+    pub added: ScopeSet,
+    /// Scopes that were removed
+    pub removed: ScopeSet,
+    /// [Deprecated](Scope::is_deprecated) scopes in the new set, see [`Scope::migrate`]
+    pub deprecated: ScopeSet,
+}
+
+impl ScopeDiff {
+    /// Returns true if no scopes were added or removed
+    pub fn is_empty(&self) -> bool { self.added.is_empty() && self.removed.is_empty() }
+}
+
+impl std::fmt::Display for ScopeDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let added = self.added.iter().map(|s| ('+', s));
+        let removed = self.removed.iter().map(|s| ('-', s));
+        for (i, (sign, scope)) in added.chain(removed).enumerate() {
+            if i != 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{sign}{scope}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The index of a known scope, also for [`Scope::Other`] with the name of a known scope.
+fn known_index(scope: &Scope) -> Option<u8> {
+    match scope {
+        Scope::Other(s) => Scope::index_of(s),
+        scope => scope.index(),
+    }
+}
+
+/// Iterator over the scopes in a [`ScopeSet`], see [`ScopeSet::iter`]
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    known: u128,
+    other: std::slice::Iter<'a, Scope>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a Scope;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.known != 0 {
+            let index = self.known.trailing_zeros();
+            self.known &= self.known - 1;
+            return Some(&Scope::KNOWN[index as usize]);
+        }
+        self.other.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.known.count_ones() as usize + self.other.len();
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl<'a> IntoIterator for &'a ScopeSet {
+    type IntoIter = Iter<'a>;
+    type Item = &'a Scope;
+
+    fn into_iter(self) -> Self::IntoIter { self.iter() }
+}
+
+/// Owning iterator over the scopes in a [`ScopeSet`]
+#[derive(Debug, Clone)]
+pub struct IntoIter {
+    known: u128,
+    other: std::vec::IntoIter<Scope>,
+}
+
+impl Iterator for IntoIter {
+    type Item = Scope;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.known != 0 {
+            let index = self.known.trailing_zeros();
+            self.known &= self.known - 1;
+            return Some(Scope::KNOWN[index as usize].clone());
+        }
+        self.other.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.known.count_ones() as usize + self.other.len();
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for IntoIter {}
+
+impl IntoIterator for ScopeSet {
+    type IntoIter = IntoIter;
+    type Item = Scope;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            known: self.known,
+            other: self.other.into_iter(),
+        }
+    }
+}
+
+impl FromIterator<Scope> for ScopeSet {
+    fn from_iter<T: IntoIterator<Item = Scope>>(iter: T) -> Self {
+        let mut set = ScopeSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<'a> FromIterator<&'a Scope> for ScopeSet {
+    fn from_iter<T: IntoIterator<Item = &'a Scope>>(iter: T) -> Self {
+        iter.into_iter().cloned().collect()
+    }
+}
+
+impl Extend<Scope> for ScopeSet {
+    fn extend<T: IntoIterator<Item = Scope>>(&mut self, iter: T) {
+        for scope in iter {
+            self.insert(scope);
+        }
+    }
+}
+
+impl From<Vec<Scope>> for ScopeSet {
+    fn from(scopes: Vec<Scope>) -> Self { scopes.into_iter().collect() }
+}
+
+impl From<&[Scope]> for ScopeSet {
+    fn from(scopes: &[Scope]) -> Self { scopes.iter().collect() }
+}
+
+impl<const N: usize> From<[Scope; N]> for ScopeSet {
+    fn from(scopes: [Scope; N]) -> Self { scopes.into_iter().collect() }
+}
+
+impl From<ScopeSet> for Vec<Scope> {
+    fn from(set: ScopeSet) -> Self { set.into_iter().collect() }
+}
+
+impl std::ops::BitOr<&ScopeSet> for &ScopeSet {
+    type Output = ScopeSet;
+
+    fn bitor(self, rhs: &ScopeSet) -> ScopeSet { self.union(rhs) }
+}
+
+impl std::ops::BitAnd<&ScopeSet> for &ScopeSet {
+    type Output = ScopeSet;
+
+    fn bitand(self, rhs: &ScopeSet) -> ScopeSet { self.intersection(rhs) }
+}
+
+impl std::ops::BitXor<&ScopeSet> for &ScopeSet {
+    type Output = ScopeSet;
+
+    fn bitxor(self, rhs: &ScopeSet) -> ScopeSet { self.symmetric_difference(rhs) }
+}
+
+impl std::ops::Sub<&ScopeSet> for &ScopeSet {
+    type Output = ScopeSet;
+
+    fn sub(self, rhs: &ScopeSet) -> ScopeSet { self.difference(rhs) }
+}
+
+impl std::fmt::Debug for ScopeSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl std::fmt::Display for ScopeSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, scope) in self.iter().enumerate() {
+            if i != 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(scope.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for ScopeSet {
+    type Err = std::convert::Infallible;
+
+    /// Parse a space separated string of scopes
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.split_whitespace()
+            .map(|s| Scope::parse(s.to_owned()))
+            .collect())
+    }
+}
+
+impl serde::Serialize for ScopeSet {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: serde::Serializer {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for ScopeSet {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: serde::Deserializer<'de> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = ScopeSet;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a space separated string or a sequence of scopes")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(v.parse().unwrap_or_default())
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where A: serde::de::SeqAccess<'de> {
+                let mut set = ScopeSet::new();
+                while let Some(scope) = seq.next_element::<Cow<'de, str>>()? {
+                    set.insert(Scope::parse(scope.into_owned()));
+                }
+                Ok(set)
+            }
+        }
+
+        // formats that aren't self-describing can't tell a string from a sequence, use what `Serialize` writes
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(Visitor)
+        } else {
+            deserializer.deserialize_str(Visitor)
+        }
+    }
+}
+
+/// A collection of scopes that can be checked for a scope, used by [`Validator`](super::Validator).
+pub trait ScopeLookup {
+    /// Returns true if the scope is present
+    fn contains_scope(&self, scope: &Scope) -> bool;
+}
+
+impl ScopeLookup for ScopeSet {
+    fn contains_scope(&self, scope: &Scope) -> bool { self.contains(scope) }
+}
+
+impl ScopeLookup for [Scope] {
+    fn contains_scope(&self, scope: &Scope) -> bool { self.contains(scope) }
+}
+
+impl<const N: usize> ScopeLookup for [Scope; N] {
+    fn contains_scope(&self, scope: &Scope) -> bool { self.contains(scope) }
+}
+
+impl ScopeLookup for Vec<Scope> {
+    fn contains_scope(&self, scope: &Scope) -> bool { self.contains(scope) }
+}
+
+impl<T: ScopeLookup + ?Sized> ScopeLookup for &T {
+    fn contains_scope(&self, scope: &Scope) -> bool { (**self).contains_scope(scope) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_order() {
+        let set: ScopeSet = [
+            Scope::Other(Cow::Borrowed("z:custom")),
+            Scope::UserReadChat,
+            Scope::Other(Cow::Borrowed("a:custom")),
+            Scope::ChatRead,
+            Scope::Other(Cow::Borrowed("chat:edit")),
+        ]
+        .into();
+        assert_eq!(
+            set.to_vec(),
+            [
+                Scope::ChatEdit,
+                Scope::ChatRead,
+                Scope::UserReadChat,
+                Scope::Other(Cow::Borrowed("a:custom")),
+                Scope::Other(Cow::Borrowed("z:custom")),
+            ]
+        );
+        assert_eq!(set.len(), 5);
+        assert!(set.contains(&Scope::Other(Cow::Borrowed("chat:read"))));
+    }
+
+    #[test]
+    fn deserialize_not_self_describing() {
+        use serde::de::{Deserialize, Deserializer, Error as _, Visitor};
+
+        /// Deserializer that only knows the type it's asked for, like bincode
+        struct Compact<'de>(&'de str);
+
+        impl<'de> Deserializer<'de> for Compact<'de> {
+            type Error = serde::de::value::Error;
+
+            serde::forward_to_deserialize_any! {
+                bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char string
+                bytes byte_buf option unit unit_struct newtype_struct seq tuple
+                tuple_struct map struct enum identifier ignored_any
+            }
+
+            fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+                Err(Self::Error::custom("not self-describing"))
+            }
+
+            fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                visitor.visit_borrowed_str(self.0)
+            }
+
+            fn is_human_readable(&self) -> bool { false }
+        }
+
+        let set = ScopeSet::deserialize(Compact("chat:read user:edit")).unwrap();
+        assert_eq!(set, [Scope::ChatRead, Scope::UserEdit].into());
+    }
+
+    #[test]
+    fn all_known() {
+        for (i, scope) in Scope::KNOWN.iter().enumerate() {
+            assert_eq!(scope.index(), Some(i as u8));
+        }
+        let mut set = ScopeSet::all();
+        assert_eq!(set.to_vec(), Scope::all());
+        for scope in Scope::all() {
+            assert!(set.remove(&scope));
+        }
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn algebra() {
+        let a: ScopeSet = "chat:edit chat:read a:custom b:custom".parse().unwrap();
+        let b: ScopeSet = "chat:read user:edit b:custom".parse().unwrap();
+        assert_eq!(
+            (&a | &b).to_string(),
+            "chat:edit chat:read user:edit a:custom b:custom"
+        );
+        assert_eq!((&a & &b).to_string(), "chat:read b:custom");
+        assert_eq!((&a - &b).to_string(), "chat:edit a:custom");
+        assert_eq!((&a ^ &b).to_string(), "chat:edit user:edit a:custom");
+        assert!((&a & &b).is_subset(&a));
+        assert!(a.is_superset(&(&a - &b)));
+        assert!((&a - &b).is_disjoint(&b));
+    }
+
+    #[test]
+    fn serde() {
+        let set: ScopeSet = serde_json::from_str(r#"["chat:read", "chat:edit"]"#).unwrap();
+        assert_eq!(set, ScopeSet::from([Scope::ChatEdit, Scope::ChatRead]));
+        let set: ScopeSet = serde_json::from_str(r#""chat:read chat:edit""#).unwrap();
+        assert_eq!(set, ScopeSet::from([Scope::ChatEdit, Scope::ChatRead]));
+        assert_eq!(
+            serde_json::to_string(&set).unwrap(),
+            r#""chat:edit chat:read""#
+        );
+    }
+}
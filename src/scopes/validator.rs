@@ -1,14 +1,23 @@
 //! Validator used for checking scopes in a token.
 use std::borrow::Cow;
 
-use super::Scope;
+use super::{Scope, ScopeLookup};
+
+mod dnf;
+mod evaluate;
+mod parse;
+mod simplify;
+pub use evaluate::Evaluation;
+pub use parse::ValidatorParseError;
 
 /// A collection of validators
 pub type Validators = Cow<'static, [Validator]>;
 
 /// A [validator](Validator) is a way to check if an array of scopes matches a predicate.
 ///
-/// Can be constructed easily with the [validator!](crate::validator) macro.
+/// Can be constructed easily with the [validator!](crate::validator) macro,
+/// or at runtime by [parsing](std::str::FromStr) a string like `all(chat:read, any(chat:edit, user:write:chat))`.
+/// Validators (de)serialize as such a string.
 ///
 /// # Examples
 ///
@@ -98,9 +107,9 @@ impl Validator {
     /// assert!(!validator.matches(&scopes[..1]));
     /// ```
     #[must_use]
-    pub fn matches(&self, scopes: &[Scope]) -> bool {
+    pub fn matches(&self, scopes: &(impl ScopeLookup + ?std::marker::Sized)) -> bool {
         match &self {
-            Validator::Scope(scope) => scopes.contains(scope),
+            Validator::Scope(scope) => scopes.contains_scope(scope),
             Validator::All(Sized(validators)) => validators.iter().all(|v| v.matches(scopes)),
             Validator::Any(Sized(validators)) => validators.iter().any(|v| v.matches(scopes)),
             Validator::Not(Sized(validator)) => !validator.iter().any(|v| v.matches(scopes)),
@@ -151,7 +160,7 @@ impl Validator {
     /// assert!(missing.matches(&[Scope::ModeratorReadChatSettings]));
     /// assert!(missing.matches(&[Scope::ModeratorManageChatSettings]));
     /// ```
-    pub fn missing(&self, scopes: &[Scope]) -> Option<Validator> {
+    pub fn missing(&self, scopes: &(impl ScopeLookup + ?std::marker::Sized)) -> Option<Validator> {
         if self.matches(scopes) {
             return None;
         }
@@ -159,7 +168,7 @@ impl Validator {
         // TODO: There's a bit of allocation going on here, maybe we can remove it with some kind of descent
         match &self {
             Validator::Scope(scope) => {
-                if scopes.contains(scope) {
+                if scopes.contains_scope(scope) {
                     None
                 } else {
                     Some(Validator::Scope(scope.clone()))
@@ -216,6 +225,49 @@ impl Validator {
         }
     }
 
+    /// Returns the scopes that need to be added to `scopes` for the validator to match.
+    ///
+    /// When there are multiple ways to satisfy the validator, e.g. with `any(...)`, the alternative requiring the fewest additional scopes is chosen,
+    /// see [`Validator::minimal_scope_sets`].
+    /// Returns an empty vec if the validator already matches,
+    /// and `None` if the validator can't be satisfied by adding scopes, e.g. when a `not(...)` matches a scope that is already present.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::{validator, Scope};
+    ///
+    /// let validator = validator!(
+    ///     Scope::ChatRead,
+    ///     any(Scope::ModeratorReadChatters, Scope::ModerationRead)
+    /// );
+    ///
+    /// let scopes = &[Scope::ChatRead];
+    /// assert_eq!(
+    ///     validator.scopes_to_add(scopes),
+    ///     Some(vec![Scope::ModeratorReadChatters])
+    /// );
+    ///
+    /// let validator = validator!(Scope::ChatRead, not(Scope::ChatEdit));
+    /// assert_eq!(validator.scopes_to_add(&[Scope::ChatEdit]), None);
+    /// ```
+    pub fn scopes_to_add(
+        &self,
+        scopes: &(impl ScopeLookup + ?std::marker::Sized),
+    ) -> Option<Vec<Scope>> {
+        self.dnf(false)
+            .into_iter()
+            // scopes are additive, a present scope that must be absent can't be fixed by adding scopes
+            .filter(|term| !term.absent.iter().any(|s| scopes.contains_scope(s)))
+            .map(|term| {
+                term.present
+                    .into_iter()
+                    .filter(|s| !scopes.contains_scope(s))
+                    .collect::<Vec<_>>()
+            })
+            .min_by_key(|added| added.len())
+    }
+
     /// Create a [Validator] which matches if the scope is present.
     pub const fn scope(scope: Scope) -> Self { Validator::Scope(scope) }
 
@@ -234,6 +286,25 @@ impl Validator {
         Validator::Not(Sized(Cow::Borrowed(std::slice::from_ref(not))))
     }
 
+    /// Create a [Validator] which matches if all validators passed inside matches true.
+    ///
+    /// Unlike [`Validator::all_multiple`], this doesn't require the validators to be `'static`.
+    pub fn all_owned(ands: impl IntoIterator<Item = Validator>) -> Self {
+        Validator::All(Sized(Cow::Owned(ands.into_iter().collect())))
+    }
+
+    /// Create a [Validator] which matches if **any** validator passed inside matches true.
+    ///
+    /// Unlike [`Validator::any_multiple`], this doesn't require the validators to be `'static`.
+    pub fn any_owned(anys: impl IntoIterator<Item = Validator>) -> Self {
+        Validator::Any(Sized(Cow::Owned(anys.into_iter().collect())))
+    }
+
+    /// Create a [Validator] which matches if the validator passed inside matches false.
+    ///
+    /// Unlike [`Validator::not`], this doesn't require the validator to be `'static`.
+    pub fn not_owned(not: Validator) -> Self { Validator::Not(Sized(Cow::Owned(vec![not]))) }
+
     /// Convert [Self] to [Self]
     ///
     /// # Notes
@@ -536,6 +607,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn scopes_to_add() {
+        const VALIDATOR: Validator = validator!(
+            Scope::ChatEdit,
+            any(all(Scope::ModerationRead, Scope::UserEdit), Scope::ChatRead),
+            any(Scope::ModerationRead, Scope::ModeratorReadChatters)
+        );
+        assert_eq!(
+            VALIDATOR.scopes_to_add(&[Scope::ChatEdit]),
+            Some(vec![Scope::ModerationRead, Scope::UserEdit])
+        );
+        assert_eq!(
+            VALIDATOR.scopes_to_add(&[Scope::ModerationRead]),
+            Some(vec![Scope::ChatEdit, Scope::UserEdit])
+        );
+
+        const NOT_VALIDATOR: Validator = validator!(
+            any(Scope::ChatRead, Scope::ModerationRead),
+            not(Scope::ChatRead)
+        );
+        assert_eq!(
+            NOT_VALIDATOR.scopes_to_add(&[]),
+            Some(vec![Scope::ModerationRead]),
+            "moderation:read satisfies the any without breaking the not"
+        );
+        assert_eq!(NOT_VALIDATOR.scopes_to_add(&[Scope::ChatRead]), None);
+    }
+
     #[test]
     fn display() {
         const COMPLEX_VALIDATOR: Validator = validator!(
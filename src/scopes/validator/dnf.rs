@@ -0,0 +1,93 @@
+//! [Disjunctive normal form](https://en.wikipedia.org/wiki/Disjunctive_normal_form) of [validators](Validator).
+use super::{Sized, Validator};
+use crate::Scope;
+
+/// A conjunction of scopes that must be present and scopes that must not be present.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct Term {
+    pub(super) present: Vec<Scope>,
+    pub(super) absent: Vec<Scope>,
+}
+
+impl Term {
+    /// Merge two terms, returns `None` if the result is a contradiction.
+    fn merge(&self, other: &Term) -> Option<Term> {
+        let mut term = self.clone();
+        for scope in &other.present {
+            if term.absent.contains(scope) {
+                return None;
+            }
+            if !term.present.contains(scope) {
+                term.present.push(scope.clone());
+            }
+        }
+        for scope in &other.absent {
+            if term.present.contains(scope) {
+                return None;
+            }
+            if !term.absent.contains(scope) {
+                term.absent.push(scope.clone());
+            }
+        }
+        Some(term)
+    }
+
+    /// Returns true if every set of scopes matching `other` also matches `self`
+    fn subsumes(&self, other: &Term) -> bool {
+        self.present.iter().all(|s| other.present.contains(s))
+            && self.absent.iter().all(|s| other.absent.contains(s))
+    }
+}
+
+/// Remove terms that are subsumed by other terms, keeping the first of equal terms.
+pub(super) fn reduce(terms: Vec<Term>) -> Vec<Term> {
+    let mut reduced: Vec<Term> = Vec::with_capacity(terms.len());
+    for term in terms {
+        if reduced.iter().any(|t| t.subsumes(&term)) {
+            continue;
+        }
+        reduced.retain(|t| !term.subsumes(t));
+        reduced.push(term);
+    }
+    reduced
+}
+
+impl Validator {
+    /// Disjunctive normal form, the validator matches if any of the terms match.
+    ///
+    /// Contradicting and subsumed terms are removed, so an unsatisfiable validator has no terms.
+    pub(super) fn dnf(&self, negate: bool) -> Vec<Term> {
+        let conjunction = |validators: &[Validator], negate: bool| {
+            validators.iter().fold(vec![Term::default()], |acc, v| {
+                let terms = v.dnf(negate);
+                reduce(
+                    acc.iter()
+                        .flat_map(|a| terms.iter().filter_map(|t| a.merge(t)))
+                        .collect(),
+                )
+            })
+        };
+        let disjunction = |validators: &[Validator], negate: bool| {
+            reduce(validators.iter().flat_map(|v| v.dnf(negate)).collect())
+        };
+        match (self, negate) {
+            (Validator::Scope(scope), false) => vec![Term {
+                present: vec![scope.clone()],
+                absent: vec![],
+            }],
+            (Validator::Scope(scope), true) => vec![Term {
+                present: vec![],
+                absent: vec![scope.clone()],
+            }],
+            (Validator::All(Sized(vs)), false) | (Validator::Any(Sized(vs)), true) => {
+                conjunction(vs, negate)
+            }
+            (Validator::Any(Sized(vs)), false) | (Validator::All(Sized(vs)), true) => {
+                disjunction(vs, negate)
+            }
+            // not(a, b) is all(not(a), not(b))
+            (Validator::Not(Sized(vs)), false) => conjunction(vs, true),
+            (Validator::Not(Sized(vs)), true) => disjunction(vs, false),
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Explainable evaluation of [validators](Validator).
+use super::{Sized, Validator};
+use crate::{Scope, ScopeLookup};
+
+/// The result of [evaluating](Validator::evaluate) a [`Validator`] against a set of scopes.
+///
+/// Forms a tree mirroring the validator, where every node tells if it passed and which scopes decided the outcome.
+///
+/// The [`Display`](std::fmt::Display) implementation renders the tree as indented text, one node per line.
+///
+/// # Examples
+///
+/// ```rust
+/// use twitch_oauth2::{validator, Scope};
+///
+/// let validator = validator!(Scope::ChatEdit, any(Scope::ChatRead, Scope::UserReadChat));
+/// let evaluation = validator.evaluate(&[Scope::ChatEdit]);
+/// assert!(!evaluation.passed);
+/// assert_eq!(evaluation.scopes, [Scope::ChatRead, Scope::UserReadChat]);
+/// assert_eq!(
+///     evaluation.to_string(),
+///     "\
+/// [fail] all
+///   [pass] chat:edit (present)
+///   [fail] any
+///     [fail] chat:read (missing)
+///     [fail] user:read:chat (missing)
+/// "
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Evaluation<'a> {
+    /// The validator this node evaluated
+    pub validator: &'a Validator,
+    /// Whether the validator matched
+    pub passed: bool,
+    /// The scopes responsible for the outcome.
+    ///
+    /// * For a scope, the scope itself.
+    /// * For `all(...)`, the scopes of the failing validators inside, or of all validators inside if it passed.
+    /// * For `any(...)`, the scopes of the passing validators inside, or of all validators inside if it failed.
+    /// * For `not(...)`, the scopes of the matching validators inside, or of all validators inside if it passed.
+    pub scopes: Vec<Scope>,
+    /// Evaluations of the validators inside, empty for a scope.
+    pub children: Vec<Evaluation<'a>>,
+}
+
+impl<'a> Evaluation<'a> {
+    fn new(validator: &'a Validator, scopes: &(impl ScopeLookup + ?std::marker::Sized)) -> Self {
+        let (children, passed) = match validator {
+            Validator::Scope(scope) => {
+                return Evaluation {
+                    validator,
+                    passed: scopes.contains_scope(scope),
+                    scopes: vec![scope.clone()],
+                    children: vec![],
+                }
+            }
+            Validator::All(Sized(validators)) => {
+                let children = evaluate_all(validators, scopes);
+                let passed = children.iter().all(|c| c.passed);
+                (children, passed)
+            }
+            Validator::Any(Sized(validators)) => {
+                let children = evaluate_all(validators, scopes);
+                let passed = children.iter().any(|c| c.passed);
+                (children, passed)
+            }
+            Validator::Not(Sized(validators)) => {
+                let children = evaluate_all(validators, scopes);
+                let passed = !children.iter().any(|c| c.passed);
+                (children, passed)
+            }
+        };
+        // the children that decided the outcome, see the docs on `scopes`
+        let deciding: fn(&Evaluation) -> bool = match (validator, passed) {
+            (Validator::All(_), false) => |c| !c.passed,
+            (Validator::Any(_), true) | (Validator::Not(_), false) => |c| c.passed,
+            _ => |_| true,
+        };
+        let mut responsible = vec![];
+        for scope in children
+            .iter()
+            .filter(|c| deciding(c))
+            .flat_map(|c| &c.scopes)
+        {
+            if !responsible.contains(scope) {
+                responsible.push(scope.clone());
+            }
+        }
+        Evaluation {
+            validator,
+            passed,
+            scopes: responsible,
+            children,
+        }
+    }
+
+    /// Iterate over the missing scopes that made this evaluation fail.
+    ///
+    /// Only failing nodes are visited, and scopes inside `not(...)` are not included, as they are present.
+    pub fn missing(&self) -> impl Iterator<Item = &Scope> + '_ {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            while let Some(node) = stack.pop() {
+                match node.validator {
+                    _ if node.passed => continue,
+                    Validator::Scope(scope) => return Some(scope),
+                    Validator::Not(_) => continue,
+                    _ => stack.extend(node.children.iter().rev()),
+                }
+            }
+            None
+        })
+    }
+
+    fn fmt_indented(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        let status = if self.passed { "[pass]" } else { "[fail]" };
+        write!(f, "{:indent$}{status} ", "", indent = depth * 2)?;
+        match self.validator {
+            Validator::Scope(scope) => {
+                let present = if self.passed { "present" } else { "missing" };
+                writeln!(f, "{scope} ({present})")?
+            }
+            Validator::All(_) => writeln!(f, "all")?,
+            Validator::Any(_) => writeln!(f, "any")?,
+            Validator::Not(_) => writeln!(f, "not")?,
+        }
+        for child in &self.children {
+            child.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+fn evaluate_all<'a>(
+    validators: &'a [Validator],
+    scopes: &(impl ScopeLookup + ?std::marker::Sized),
+) -> Vec<Evaluation<'a>> {
+    validators
+        .iter()
+        .map(|v| Evaluation::new(v, scopes))
+        .collect()
+}
+
+impl std::fmt::Display for Evaluation<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { self.fmt_indented(f, 0) }
+}
+
+impl Validator {
+    /// Evaluate the validator against the given scopes, explaining why it matched or not.
+    ///
+    /// See [`Evaluation`]
+    pub fn evaluate<'a>(
+        &'a self,
+        scopes: &(impl ScopeLookup + ?std::marker::Sized),
+    ) -> Evaluation<'a> {
+        Evaluation::new(self, scopes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator;
+
+    #[test]
+    fn evaluate_not() {
+        static VALIDATOR: Validator = validator!(
+            not(Scope::ChatEdit),
+            any(Scope::ChatRead, all(Scope::ModerationRead, Scope::UserEdit))
+        );
+        let scopes = &[Scope::ChatEdit, Scope::ModerationRead];
+        let evaluation = VALIDATOR.evaluate(scopes);
+        assert_eq!(evaluation.passed, VALIDATOR.matches(scopes));
+        assert_eq!(
+            evaluation.scopes,
+            [Scope::ChatEdit, Scope::ChatRead, Scope::UserEdit]
+        );
+        assert_eq!(
+            evaluation.missing().collect::<Vec<_>>(),
+            [&Scope::ChatRead, &Scope::UserEdit]
+        );
+        assert_eq!(
+            evaluation.to_string(),
+            "\
+[fail] all
+  [fail] not
+    [pass] chat:edit (present)
+  [fail] any
+    [fail] chat:read (missing)
+    [fail] all
+      [pass] moderation:read (present)
+      [fail] user:edit (missing)
+"
+        );
+    }
+
+    #[test]
+    fn evaluate_pass() {
+        static VALIDATOR: Validator = validator!(any(Scope::ChatRead, Scope::UserReadChat));
+        let evaluation = VALIDATOR.evaluate(&[Scope::UserReadChat]);
+        assert!(evaluation.passed);
+        assert_eq!(evaluation.scopes, [Scope::UserReadChat]);
+        assert_eq!(evaluation.missing().count(), 0);
+    }
+}
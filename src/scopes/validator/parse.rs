@@ -0,0 +1,330 @@
+//! Parsing of [validators](Validator) from strings.
+use std::borrow::Cow;
+
+use super::{Sized, Validator};
+use crate::Scope;
+
+/// Errors from parsing a [`Validator`] from a string
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error, displaydoc::Display)]
+#[non_exhaustive]
+pub enum ValidatorParseError {
+    /// unexpected end of input, expected {expected}
+    UnexpectedEnd {
+        /// What was expected
+        expected: &'static str,
+    },
+    /// unexpected `{found}` at position {position}, expected {expected}
+    Unexpected {
+        /// What was found
+        found: String,
+        /// Byte position in the input
+        position: usize,
+        /// What was expected
+        expected: &'static str,
+    },
+    /// unknown operator `{operator}` at position {position}, only `all`, `any` and `not` are supported
+    UnknownOperator {
+        /// The operator
+        operator: String,
+        /// Byte position in the input
+        position: usize,
+    },
+    /// `and` and `or` can not be mixed without parentheses, at position {position}
+    MixedOperators {
+        /// Byte position in the input
+        position: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    Open,
+    Close,
+    Comma,
+    Word(&'a str),
+}
+
+impl std::fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Open => f.write_str("("),
+            Token::Close => f.write_str(")"),
+            Token::Comma => f.write_str(","),
+            Token::Word(word) => f.write_str(word),
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Returns the next token and its position without consuming it
+    fn peek(&mut self) -> Option<(Token<'a>, usize)> {
+        self.skip_whitespace();
+        let rest = &self.input[self.position..];
+        let token = match rest.chars().next()? {
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | ','))
+                    .unwrap_or(rest.len());
+                Token::Word(&rest[..end])
+            }
+        };
+        Some((token, self.position))
+    }
+
+    fn next(&mut self) -> Option<(Token<'a>, usize)> {
+        let (token, position) = self.peek()?;
+        self.position += match token {
+            Token::Word(word) => word.len(),
+            _ => 1,
+        };
+        Some((token, position))
+    }
+
+    fn expect(
+        &mut self,
+        expected: Token<'static>,
+        what: &'static str,
+    ) -> Result<(), ValidatorParseError> {
+        match self.next() {
+            Some((token, _)) if token == expected => Ok(()),
+            Some((found, position)) => Err(ValidatorParseError::Unexpected {
+                found: found.to_string(),
+                position,
+                expected: what,
+            }),
+            None => Err(ValidatorParseError::UnexpectedEnd { expected: what }),
+        }
+    }
+
+    /// `scope`, `operator(...)` or `(...)`
+    fn term(&mut self) -> Result<Validator, ValidatorParseError> {
+        match self.next() {
+            Some((Token::Open, _)) => {
+                let validator = self.infix(true)?;
+                self.expect(Token::Close, "`)`")?;
+                Ok(validator)
+            }
+            Some((Token::Word(word), position)) => {
+                if !matches!(self.peek(), Some((Token::Open, _))) {
+                    return Ok(Validator::Scope(Scope::parse(word.to_owned())));
+                }
+                self.next();
+                let validators = self.arguments()?;
+                match word {
+                    "all" => Ok(Validator::all_owned(validators)),
+                    "any" => Ok(Validator::any_owned(validators)),
+                    "not" if validators.is_empty() => Err(ValidatorParseError::Unexpected {
+                        found: ")".to_owned(),
+                        position: self.position - 1,
+                        expected: "a validator",
+                    }),
+                    "not" => Ok(Validator::Not(Sized(Cow::Owned(validators)))),
+                    operator => Err(ValidatorParseError::UnknownOperator {
+                        operator: operator.to_owned(),
+                        position,
+                    }),
+                }
+            }
+            Some((found, position)) => Err(ValidatorParseError::Unexpected {
+                found: found.to_string(),
+                position,
+                expected: "a scope, an operator or `(`",
+            }),
+            None => Err(ValidatorParseError::UnexpectedEnd {
+                expected: "a scope, an operator or `(`",
+            }),
+        }
+    }
+
+    /// comma separated validators, ending with `)`
+    fn arguments(&mut self) -> Result<Vec<Validator>, ValidatorParseError> {
+        let mut validators = vec![];
+        if matches!(self.peek(), Some((Token::Close, _))) {
+            self.next();
+            return Ok(validators);
+        }
+        loop {
+            validators.push(self.infix(false)?);
+            match self.next() {
+                Some((Token::Comma, _)) => continue,
+                Some((Token::Close, _)) => return Ok(validators),
+                Some((found, position)) => {
+                    return Err(ValidatorParseError::Unexpected {
+                        found: found.to_string(),
+                        position,
+                        expected: "`,` or `)`",
+                    })
+                }
+                None => {
+                    return Err(ValidatorParseError::UnexpectedEnd {
+                        expected: "`,` or `)`",
+                    })
+                }
+            }
+        }
+    }
+
+    /// terms separated by either `and` or `or`
+    ///
+    /// If `empty` is true, no terms is accepted and means an empty `all(...)`.
+    fn infix(&mut self, empty: bool) -> Result<Validator, ValidatorParseError> {
+        if empty && matches!(self.peek(), Some((Token::Close, _)) | None) {
+            return Ok(Validator::all_owned([]));
+        }
+        let mut validators = vec![self.term()?];
+        let mut operator = None;
+        while let Some((Token::Word(word @ ("and" | "or")), position)) = self.peek() {
+            if operator.is_some_and(|op| op != word) {
+                return Err(ValidatorParseError::MixedOperators { position });
+            }
+            operator = Some(word);
+            self.next();
+            validators.push(self.term()?);
+        }
+        match operator {
+            None => Ok(validators.remove(0)),
+            Some("and") => Ok(Validator::all_owned(validators)),
+            Some(_) => Ok(Validator::any_owned(validators)),
+        }
+    }
+}
+
+impl std::str::FromStr for Validator {
+    type Err = ValidatorParseError;
+
+    /// Parse a validator.
+    ///
+    /// Accepts the format of the [`Display`](std::fmt::Display) implementation, `(a and b)`, `(a or b)` and `not(a)`,
+    /// as well as the operators used in the [validator!](crate::validator) macro, `all(a, b)`, `any(a, b)` and `not(a)`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            input: s,
+            position: 0,
+        };
+        let validator = parser.infix(true)?;
+        match parser.next() {
+            None => Ok(validator),
+            Some((found, position)) => Err(ValidatorParseError::Unexpected {
+                found: found.to_string(),
+                position,
+                expected: "end of input",
+            }),
+        }
+    }
+}
+
+impl serde::Serialize for Validator {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: serde::Serializer {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Validator {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: serde::Deserializer<'de> {
+        let s: Cow<'de, str> = serde::Deserialize::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator;
+
+    #[test]
+    fn roundtrip_display() {
+        const VALIDATORS: &[Validator] = &[
+            validator!(),
+            validator!(Scope::ChatEdit),
+            validator!(Scope::ChatEdit, Scope::ChatRead),
+            validator!(
+                not(Scope::ChatEdit),
+                any(Scope::ChatRead, all(Scope::ModerationRead, Scope::UserEdit))
+            ),
+            validator!(any(
+                not(any(Scope::ChatEdit, Scope::UserEdit)),
+                Scope::ChatRead
+            )),
+        ];
+        for validator in VALIDATORS {
+            let parsed: Validator = validator.to_string().parse().unwrap();
+            assert_eq!(&parsed, validator, "{validator}");
+        }
+    }
+
+    #[test]
+    fn parse_operators() {
+        let parsed: Validator = "all(chat:edit, any(chat:read, custom:scope), not(user:edit))"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            parsed,
+            validator!(
+                Scope::ChatEdit,
+                any(Scope::ChatRead, Scope::Other(Cow::Borrowed("custom:scope"))),
+                not(Scope::UserEdit)
+            )
+        );
+        let parsed: Validator = "chat:edit and (chat:read or user:edit)".parse().unwrap();
+        assert_eq!(
+            parsed,
+            validator!(Scope::ChatEdit, any(Scope::ChatRead, Scope::UserEdit))
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            "chat:edit and chat:read or user:edit".parse::<Validator>(),
+            Err(ValidatorParseError::MixedOperators { position: 24 })
+        );
+        assert_eq!(
+            "xor(chat:edit, chat:read)".parse::<Validator>(),
+            Err(ValidatorParseError::UnknownOperator {
+                operator: "xor".to_owned(),
+                position: 0
+            })
+        );
+        assert_eq!(
+            "all(chat:edit".parse::<Validator>(),
+            Err(ValidatorParseError::UnexpectedEnd {
+                expected: "`,` or `)`"
+            })
+        );
+        assert!("not()".parse::<Validator>().is_err());
+        assert!("(chat:edit))".parse::<Validator>().is_err());
+    }
+
+    #[test]
+    fn serde() {
+        #[derive(serde_derive::Deserialize, serde_derive::Serialize)]
+        struct Config {
+            required: Validator,
+        }
+        let config: Config =
+            serde_json::from_str(r#"{"required": "any(chat:read, user:read:chat)"}"#).unwrap();
+        assert_eq!(
+            config.required,
+            validator!(any(Scope::ChatRead, Scope::UserReadChat))
+        );
+        assert_eq!(
+            serde_json::to_string(&config).unwrap(),
+            r#"{"required":"(chat:read or user:read:chat)"}"#
+        );
+    }
+}
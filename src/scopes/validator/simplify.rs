@@ -0,0 +1,237 @@
+//! Normalization and analysis of [validators](Validator).
+use std::borrow::Cow;
+
+use super::dnf::{reduce, Term};
+use super::{Sized, Validator};
+use crate::Scope;
+
+impl Validator {
+    /// Returns a simplified validator that matches the same scopes.
+    ///
+    /// * nested `all(...)` and `any(...)` are flattened
+    /// * duplicate validators are removed
+    /// * `not(...)` is pushed down to the scopes with [De Morgan's laws](https://en.wikipedia.org/wiki/De_Morgan%27s_laws)
+    /// * contradictions like `all(x, not(x))` become `any()`, which never matches,
+    ///   and tautologies like `any(x, not(x))` become `all()`, which always matches.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::{validator, Scope, Validator};
+    ///
+    /// const CHAT_SCOPES: Validator = validator!(all(Scope::ChatEdit, Scope::ChatRead));
+    /// const MODERATOR_SCOPES: Validator = validator!(Scope::ModerationRead, Scope::ChatRead);
+    /// const COMBINED: Validator = validator!(CHAT_SCOPES, MODERATOR_SCOPES);
+    /// assert_eq!(
+    ///     COMBINED.simplify(),
+    ///     validator!(Scope::ChatEdit, Scope::ChatRead, Scope::ModerationRead)
+    /// );
+    ///
+    /// const NOT: Validator = validator!(not(any(Scope::ChatEdit, not(Scope::ChatRead))));
+    /// assert_eq!(
+    ///     NOT.simplify(),
+    ///     validator!(not(Scope::ChatEdit), Scope::ChatRead)
+    /// );
+    /// ```
+    pub fn simplify(&self) -> Validator { self.simplify_inner(false) }
+
+    fn simplify_inner(&self, negate: bool) -> Validator {
+        match (self, negate) {
+            (Validator::Scope(_), false) => self.clone(),
+            (Validator::Scope(_), true) => Validator::not_owned(self.clone()),
+            (Validator::All(Sized(vs)), false) | (Validator::Any(Sized(vs)), true) => {
+                combine(true, vs.iter().map(|v| v.simplify_inner(negate)))
+            }
+            (Validator::Any(Sized(vs)), false) | (Validator::All(Sized(vs)), true) => {
+                combine(false, vs.iter().map(|v| v.simplify_inner(negate)))
+            }
+            (Validator::Not(Sized(vs)), false) => {
+                combine(true, vs.iter().map(|v| v.simplify_inner(true)))
+            }
+            (Validator::Not(Sized(vs)), true) => {
+                combine(false, vs.iter().map(|v| v.simplify_inner(false)))
+            }
+        }
+    }
+
+    /// Returns true if there is a set of scopes that this validator matches.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::{validator, Scope};
+    ///
+    /// assert!(validator!(Scope::ChatEdit, not(Scope::ChatRead)).is_satisfiable());
+    /// assert!(
+    ///     !validator!(Scope::ChatEdit, not(any(Scope::ChatRead, Scope::ChatEdit))).is_satisfiable()
+    /// );
+    /// ```
+    pub fn is_satisfiable(&self) -> bool { !self.dnf(false).is_empty() }
+
+    /// Returns the minimal sets of scopes that this validator matches.
+    ///
+    /// Every returned set matches the validator, and no scope can be removed from a set without it no longer matching.
+    /// Returns an empty vec if the validator [is not satisfiable](Validator::is_satisfiable).
+    ///
+    /// # Notes
+    ///
+    /// This expands the validator into all combinations of its `any(...)` branches, which grows exponentially with the amount of nested `any(...)`s.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::{validator, Scope};
+    ///
+    /// let validator = validator!(
+    ///     Scope::ChatEdit,
+    ///     any(
+    ///         Scope::ChatRead,
+    ///         all(Scope::ChatRead, Scope::UserReadChat),
+    ///         Scope::UserReadChat
+    ///     )
+    /// );
+    /// assert_eq!(
+    ///     validator.minimal_scope_sets(),
+    ///     vec![
+    ///         vec![Scope::ChatEdit, Scope::ChatRead],
+    ///         vec![Scope::ChatEdit, Scope::UserReadChat],
+    ///     ]
+    /// );
+    /// ```
+    pub fn minimal_scope_sets(&self) -> Vec<Vec<Scope>> {
+        let terms: Vec<Term> = self
+            .dnf(false)
+            .into_iter()
+            .map(|t| Term {
+                present: t.present,
+                absent: vec![],
+            })
+            .collect();
+        reduce(terms).into_iter().map(|t| t.present).collect()
+    }
+
+    /// Returns true if the validators match exactly the same sets of scopes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::{validator, Scope};
+    ///
+    /// let a = validator!(not(any(Scope::ChatEdit, Scope::ChatRead)));
+    /// let b = validator!(not(Scope::ChatEdit), not(Scope::ChatRead));
+    /// assert!(a.is_equivalent(&b));
+    /// assert!(!a.is_equivalent(&validator!(not(Scope::ChatEdit))));
+    /// ```
+    pub fn is_equivalent(&self, other: &Validator) -> bool {
+        let only_self = Validator::all_owned([self.clone(), Validator::not_owned(other.clone())]);
+        let only_other = Validator::all_owned([other.clone(), Validator::not_owned(self.clone())]);
+        !only_self.is_satisfiable() && !only_other.is_satisfiable()
+    }
+}
+
+/// Combine already simplified validators into an `all(...)` if `and` is true, otherwise an `any(...)`.
+fn combine(and: bool, validators: impl Iterator<Item = Validator>) -> Validator {
+    let mut flattened: Vec<Validator> = vec![];
+    for validator in validators {
+        match validator {
+            Validator::All(Sized(inner)) if and => flattened.extend(inner.iter().cloned()),
+            Validator::Any(Sized(inner)) if !and => flattened.extend(inner.iter().cloned()),
+            // an empty any(...) never matches, an empty all(...) always matches
+            Validator::Any(Sized(inner)) if and && inner.is_empty() => return never(),
+            Validator::All(Sized(inner)) if !and && inner.is_empty() => return always(),
+            validator => flattened.push(validator),
+        }
+    }
+    let mut deduped: Vec<Validator> = Vec::with_capacity(flattened.len());
+    for validator in flattened {
+        if deduped.contains(&validator) {
+            continue;
+        }
+        let negated = match &validator {
+            Validator::Not(Sized(inner)) if inner.len() == 1 => inner[0].clone(),
+            validator => Validator::not_owned(validator.clone()),
+        };
+        if deduped.contains(&negated) {
+            // x and not(x) never matches, x or not(x) always matches
+            return if and { never() } else { always() };
+        }
+        deduped.push(validator);
+    }
+    match (deduped.len(), and) {
+        (1, _) => deduped.remove(0),
+        (_, true) => Validator::All(Sized(Cow::Owned(deduped))),
+        (_, false) => Validator::Any(Sized(Cow::Owned(deduped))),
+    }
+}
+
+fn always() -> Validator { Validator::all_multiple(&[]) }
+
+fn never() -> Validator { Validator::any_multiple(&[]) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator;
+
+    #[test]
+    fn simplify_constants() {
+        assert_eq!(
+            validator!(Scope::ChatEdit, not(Scope::ChatEdit)).simplify(),
+            never()
+        );
+        assert_eq!(
+            validator!(any(Scope::ChatEdit, not(Scope::ChatEdit))).simplify(),
+            always()
+        );
+        assert_eq!(
+            Validator::all_owned([
+                Validator::Scope(Scope::ChatEdit),
+                Validator::any_owned([Validator::Scope(Scope::ChatRead), always()])
+            ])
+            .simplify(),
+            validator!(Scope::ChatEdit)
+        );
+        assert_eq!(
+            validator!(not(not(Scope::ChatEdit))).simplify(),
+            validator!(Scope::ChatEdit)
+        );
+    }
+
+    #[test]
+    fn simplify_is_equivalent() {
+        const VALIDATORS: &[Validator] = &[
+            validator!(
+                not(all(Scope::ChatEdit, Scope::ModerationRead)),
+                Scope::ChatRead,
+                Scope::UserEdit,
+                any(Scope::ModerationRead, not(Scope::UserEdit))
+            ),
+            validator!(any(
+                all(Scope::ChatEdit, any(Scope::ChatRead, Scope::ChatEdit)),
+                not(any(Scope::UserEdit, all(Scope::ChatRead)))
+            )),
+        ];
+        for validator in VALIDATORS {
+            let simplified = validator.simplify();
+            assert!(validator.is_equivalent(&simplified), "{validator}");
+        }
+    }
+
+    #[test]
+    fn satisfiable() {
+        const VALIDATOR: Validator = validator!(
+            not(all(Scope::ChatEdit, Scope::ModerationRead)),
+            Scope::ChatRead,
+            Scope::UserEdit,
+            any(Scope::ModerationRead, not(Scope::UserEdit))
+        );
+        assert!(VALIDATOR.is_satisfiable());
+        assert_eq!(
+            VALIDATOR.minimal_scope_sets(),
+            [[Scope::ChatRead, Scope::UserEdit, Scope::ModerationRead]]
+        );
+        assert!(!validator!(Scope::ChatEdit, not(Scope::ChatEdit)).is_satisfiable());
+        assert!(validator!().is_satisfiable());
+        assert_eq!(validator!().minimal_scope_sets(), [Vec::<Scope>::new()]);
+    }
+}
@@ -0,0 +1,348 @@
+//! Storage of pending authorizations for the [OAuth authorization code flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#authorization-code-grant-flow)
+//!
+//! When the user is redirected back to your server, the callback is usually handled by another request handler than the one that sent the user to twitch,
+//! so the [`UserTokenBuilder`](crate::tokens::UserTokenBuilder) isn't available anymore.
+//! A [`StateStore`] keeps what is needed to rebuild the builder, keyed by the [CSRF token](CsrfToken) sent as `state`.
+//!
+//! A stored state only proves that the login was started by your server, not by the browser completing it.
+//! To protect against [login CSRF](https://datatracker.ietf.org/doc/html/rfc6749#section-10.12), also store the CSRF token in the browser,
+//! e.g. in an `HttpOnly` cookie, and check that it [matches](CsrfTokenRef::matches) the `state` in the callback.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # async move {
+//! use std::time::Duration;
+//! use twitch_oauth2::{state::MemoryStateStore, tokens::UserTokenBuilder, Scope};
+//! use url::Url;
+//!
+//! let store = MemoryStateStore::new(Duration::from_secs(10 * 60));
+//! let redirect_url = Url::parse("http://localhost/twitch/register")?;
+//!
+//! // in the login handler
+//! let mut builder = UserTokenBuilder::new("myclientid", "myclientsecret", redirect_url)
+//!     .set_scopes(vec![Scope::ChatRead]);
+//! let (url, _) = builder.generate_url_stored(&store).await?;
+//! // redirect the user to `url`
+//!
+//! // in the callback handler, with `state` and `code` from the query
+//! # let state = url.query_pairs().find(|(k, _)| k == "state").unwrap().1.into_owned();
+//! let builder = UserTokenBuilder::from_store(&store, "myclientid", "myclientsecret", &state)
+//!     .await?
+//!     .expect("unknown or expired state");
+//! assert!(builder.csrf_is_valid(&state));
+//! // let token = builder.get_user_token(&client, &state, &code).await?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! # };
+//! ```
+//!
+//! With the `signed_state` feature, `StateSigner` can be used instead of a store, embedding the state in a signed `state` parameter.
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::hash::BuildHasher;
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[cfg(not(all(target_family = "wasm", target_os = "unknown")))]
+use std::time::Instant;
+
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
+use web_time::Instant;
+
+use crate::{CsrfToken, CsrfTokenRef, Scope};
+
+#[cfg(feature = "signed_state")]
+mod signed;
+#[cfg(feature = "signed_state")]
+pub use signed::{SignedState, SignedStateError, StateSigner};
+
+/// The state of a pending authorization, stored in a [`StateStore`] until the user is redirected back.
+#[derive(Debug, Clone, PartialEq, Eq, serde_derive::Serialize, serde_derive::Deserialize)]
+#[non_exhaustive]
+pub struct AuthorizationState {
+    /// Scopes that were requested
+    pub scopes: Vec<Scope>,
+    /// The redirect url, twitch requires it to be the same when exchanging the code
+    pub redirect_url: url::Url,
+    /// Whether the user was forced to verify the authorization
+    pub force_verify: bool,
+}
+
+impl AuthorizationState {
+    /// Create a new state
+    pub fn new(scopes: Vec<Scope>, redirect_url: url::Url, force_verify: bool) -> Self {
+        Self {
+            scopes,
+            redirect_url,
+            force_verify,
+        }
+    }
+}
+
+/// Storage for [pending authorizations](AuthorizationState), keyed by the CSRF token sent as `state`.
+///
+/// Implementations should only return a state once, and compare tokens in constant time, see [`CsrfTokenRef::matches`].
+pub trait StateStore: Sync + Send {
+    /// Error returned by the store
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Store the state of an authorization
+    fn insert(
+        &self,
+        csrf: CsrfToken,
+        state: AuthorizationState,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Remove and return the state of an authorization, returns `None` if it's unknown or expired.
+    fn take(
+        &self,
+        csrf: &CsrfTokenRef,
+    ) -> impl Future<Output = Result<Option<AuthorizationState>, Self::Error>> + Send;
+}
+
+/// An in-memory [`StateStore`] where states expire after a time to live.
+///
+/// Entries are bucketed by a randomly keyed hash, and the tokens are then compared in constant time.
+///
+/// At most [`max_len`](MemoryStateStore::set_max_len) states are stored, when the store is full the oldest state is removed to make room.
+#[derive(Debug)]
+pub struct MemoryStateStore {
+    ttl: Duration,
+    max_len: usize,
+    hasher: std::collections::hash_map::RandomState,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    buckets: HashMap<u64, Vec<Entry>>,
+    /// Keys and ids of the states in insertion order, which is also the order they expire in.
+    ///
+    /// States that were taken are left in here until they reach the front.
+    order: VecDeque<(Instant, u64, u64)>,
+    len: usize,
+    next_id: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    id: u64,
+    csrf: CsrfToken,
+    state: AuthorizationState,
+}
+
+impl Entries {
+    /// Remove the state with `id` from the bucket `key`, returns `None` if it was already taken
+    fn remove(&mut self, key: u64, id: u64) -> Option<Entry> {
+        let bucket = self.buckets.get_mut(&key)?;
+        let position = bucket.iter().position(|e| e.id == id)?;
+        let entry = bucket.swap_remove(position);
+        if bucket.is_empty() {
+            self.buckets.remove(&key);
+        }
+        self.len -= 1;
+        Some(entry)
+    }
+
+    fn remove_expired(&mut self, now: Instant) {
+        while let Some(&(expires, key, id)) = self.order.front() {
+            if expires > now {
+                break;
+            }
+            self.order.pop_front();
+            self.remove(key, id);
+        }
+    }
+
+    /// Remove the oldest state
+    fn remove_oldest(&mut self) {
+        while let Some((_, key, id)) = self.order.pop_front() {
+            if self.remove(key, id).is_some() {
+                break;
+            }
+        }
+    }
+}
+
+impl MemoryStateStore {
+    /// Create a store where states expire after `ttl`, storing at most 10 000 states
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            max_len: 10_000,
+            hasher: Default::default(),
+            entries: Mutex::default(),
+        }
+    }
+
+    /// Set the maximum number of stored states
+    pub fn set_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len.max(1);
+        self
+    }
+
+    /// Get the time to live of states
+    pub fn ttl(&self) -> Duration { self.ttl }
+
+    /// Get the maximum number of stored states
+    pub fn max_len(&self) -> usize { self.max_len }
+
+    /// Returns the number of stored states, including expired states that haven't been removed yet
+    pub fn len(&self) -> usize { self.lock().len }
+
+    /// Returns true if there are no stored states
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Remove all expired states
+    pub fn remove_expired(&self) { self.lock().remove_expired(Instant::now()) }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl StateStore for MemoryStateStore {
+    type Error = std::convert::Infallible;
+
+    fn insert(
+        &self,
+        csrf: CsrfToken,
+        state: AuthorizationState,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let key = self.hasher.hash_one(csrf.as_str());
+        let mut entries = self.lock();
+        // taken under the lock, so the order is sorted by expiry
+        let now = Instant::now();
+        entries.remove_expired(now);
+        if entries.len >= self.max_len {
+            entries.remove_oldest();
+        }
+        // states that were taken are only removed from the order when they reach the front, drop them before it grows unbounded
+        if entries.order.len() >= 2 * self.max_len {
+            let Entries { buckets, order, .. } = &mut *entries;
+            order.retain(|(_, key, id)| {
+                buckets
+                    .get(key)
+                    .is_some_and(|bucket| bucket.iter().any(|e| e.id == *id))
+            });
+        }
+        let id = entries.next_id;
+        entries.next_id += 1;
+        entries.order.push_back((now + self.ttl, key, id));
+        entries
+            .buckets
+            .entry(key)
+            .or_default()
+            .push(Entry { id, csrf, state });
+        entries.len += 1;
+        std::future::ready(Ok(()))
+    }
+
+    fn take(
+        &self,
+        csrf: &CsrfTokenRef,
+    ) -> impl Future<Output = Result<Option<AuthorizationState>, Self::Error>> + Send {
+        let key = self.hasher.hash_one(csrf.as_str());
+        let mut entries = self.lock();
+        entries.remove_expired(Instant::now());
+        let id = entries.buckets.get(&key).and_then(|bucket| {
+            bucket
+                .iter()
+                .find(|e| e.csrf.matches(csrf.as_str()))
+                .map(|e| e.id)
+        });
+        let state = id.and_then(|id| entries.remove(key, id)).map(|e| e.state);
+        std::future::ready(Ok(state))
+    }
+}
+
+/// Compare two byte slices in constant time, only leaking the length.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    // make sure the comparison isn't short circuited
+    std::hint::black_box(diff) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn memory_store() {
+        let store = MemoryStateStore::new(Duration::from_secs(60));
+        let state = AuthorizationState::new(
+            vec![Scope::ChatRead],
+            url::Url::parse("http://localhost/twitch/register").unwrap(),
+            false,
+        );
+        let csrf = CsrfToken::new_random();
+        store.insert(csrf.clone(), state.clone()).await.unwrap();
+        store
+            .insert(CsrfToken::new_random(), state.clone())
+            .await
+            .unwrap();
+        assert_eq!(store.len(), 2);
+
+        assert_eq!(store.take(CsrfTokenRef::from_str("wrong")).await, Ok(None));
+        assert_eq!(store.take(&csrf).await, Ok(Some(state)));
+        assert_eq!(
+            store.take(&csrf).await,
+            Ok(None),
+            "states are only returned once"
+        );
+        assert_eq!(store.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn memory_store_expires() {
+        let store = MemoryStateStore::new(Duration::ZERO);
+        let csrf = CsrfToken::new_random();
+        let state = AuthorizationState::new(
+            vec![],
+            url::Url::parse("http://localhost/twitch/register").unwrap(),
+            false,
+        );
+        store.insert(csrf.clone(), state).await.unwrap();
+        assert_eq!(store.take(&csrf).await, Ok(None));
+        assert!(store.is_empty());
+    }
+
+    #[tokio::test]
+    async fn memory_store_full() {
+        let store = MemoryStateStore::new(Duration::from_secs(60)).set_max_len(2);
+        let state = AuthorizationState::new(
+            vec![],
+            url::Url::parse("http://localhost/twitch/register").unwrap(),
+            false,
+        );
+        let csrfs = [(); 3].map(|_| CsrfToken::new_random());
+        for csrf in &csrfs {
+            store.insert(csrf.clone(), state.clone()).await.unwrap();
+        }
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.take(&csrfs[0]).await, Ok(None), "oldest is removed");
+        assert!(store.take(&csrfs[1]).await.unwrap().is_some());
+        assert!(store.take(&csrfs[2]).await.unwrap().is_some());
+        assert!(store.is_empty());
+
+        for _ in 0..10 {
+            let csrf = CsrfToken::new_random();
+            store.insert(csrf.clone(), state.clone()).await.unwrap();
+            assert!(store.take(&csrf).await.unwrap().is_some());
+        }
+        assert!(
+            store.lock().order.len() <= 4,
+            "taken states are dropped from the order"
+        );
+    }
+
+    #[test]
+    fn constant_time() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"ab"));
+    }
+}
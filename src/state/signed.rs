@@ -0,0 +1,285 @@
+//! Stateless `state` parameters, signed with a server key.
+use std::time::Duration;
+
+#[cfg(not(all(target_family = "wasm", target_os = "unknown")))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use super::AuthorizationState;
+use crate::CsrfToken;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signs and verifies self-contained `state` parameters, so that no state has to be stored on the server.
+///
+/// The state embeds the [authorization](AuthorizationState), an expiry, a random nonce and data defined by the application,
+/// e.g. the page to return to after logging in. It is signed with HMAC-SHA256.
+///
+/// A signature only proves that the server issued the state, not that it was issued to the browser completing the login.
+/// To protect against [login CSRF](https://datatracker.ietf.org/doc/html/rfc6749#section-10.12), every state is bound to a
+/// random `binding` value that the login handler stores in the browser, e.g. in an `HttpOnly` cookie, and that the callback handler passes to [`StateSigner::verify`].
+/// Only a hash of the binding is embedded in the state.
+///
+/// The data is only signed, not encrypted, so it's readable by the user.
+/// A signed state can be replayed by the same browser any number of times until it expires,
+/// use a [`StateStore`](super::StateStore) if states must be single use.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::time::Duration;
+/// use twitch_oauth2::{state::StateSigner, tokens::UserTokenBuilder, Scope};
+/// use url::Url;
+///
+/// let signer = StateSigner::new(
+///     b"a long random server key".to_vec(),
+///     Duration::from_secs(600),
+/// );
+/// let redirect_url = Url::parse("http://localhost/twitch/register")?;
+///
+/// // in the login handler
+/// let binding = twitch_oauth2::CsrfToken::new_random();
+/// let mut builder = UserTokenBuilder::new("myclientid", "myclientsecret", redirect_url)
+///     .set_scopes(vec![Scope::ChatRead]);
+/// let (url, _) = builder.generate_url_signed(&signer, "/dashboard", binding.secret());
+/// // set an `HttpOnly` cookie with `binding` and redirect the user to `url`
+///
+/// // in the callback handler, on any server sharing the key, with `binding` from the cookie
+/// # let state = url.query_pairs().find(|(k, _)| k == "state").unwrap().1.into_owned();
+/// let (builder, data) = UserTokenBuilder::from_signed_state(
+///     &signer,
+///     "myclientid",
+///     "myclientsecret",
+///     &state,
+///     binding.secret(),
+/// )?;
+/// assert_eq!(data, "/dashboard");
+/// // let token = builder.get_user_token(&client, &state, &code).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct StateSigner {
+    key: Vec<u8>,
+    ttl: Duration,
+}
+
+impl std::fmt::Debug for StateSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StateSigner")
+            .field("key", &"[redacted]")
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for StateSigner {
+    fn drop(&mut self) { zeroize::Zeroize::zeroize(&mut self.key) }
+}
+
+/// A verified signed state, returned by [`StateSigner::verify`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SignedState {
+    /// The authorization
+    pub state: AuthorizationState,
+    /// Data defined by the application
+    pub data: String,
+    /// When the state expires
+    pub expires_at: SystemTime,
+}
+
+/// Errors for [`StateSigner::verify`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error, displaydoc::Display)]
+#[non_exhaustive]
+pub enum SignedStateError {
+    /// state is malformed
+    Malformed,
+    /// state has an invalid signature
+    InvalidSignature,
+    /// state has expired
+    Expired,
+    /// state was issued to another browser
+    BindingMismatch,
+}
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize)]
+struct Payload {
+    exp: u64,
+    nonce: String,
+    /// Hash of the binding
+    bind: String,
+    data: String,
+    #[serde(flatten)]
+    state: AuthorizationState,
+}
+
+impl StateSigner {
+    /// Create a signer with a server key, signed states are valid for `ttl`.
+    ///
+    /// The key should be at least 32 random bytes, and shared by all servers that handle the callback.
+    pub fn new(key: impl Into<Vec<u8>>, ttl: Duration) -> Self {
+        Self {
+            key: key.into(),
+            ttl,
+        }
+    }
+
+    /// Get the time signed states are valid for
+    pub fn ttl(&self) -> Duration { self.ttl }
+
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.key).expect("hmac accepts keys of any length")
+    }
+
+    /// Create a signed state bound to `binding`, to be sent as the `state` parameter.
+    ///
+    /// `binding` should be a random value stored in the browser starting the login, see [`StateSigner`].
+    pub fn sign(&self, state: AuthorizationState, data: &str, binding: &str) -> CsrfToken {
+        let exp = (SystemTime::now() + self.ttl)
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let payload = Payload {
+            exp,
+            nonce: CsrfToken::new_random().secret().to_owned(),
+            bind: hash_binding(binding),
+            data: data.to_owned(),
+            state,
+        };
+        let payload = serde_json::to_vec(&payload).expect("serializing a signed state can't fail");
+        let mut mac = self.mac();
+        mac.update(&payload);
+        let signature = mac.finalize().into_bytes();
+
+        let mut encoded = URL_SAFE_NO_PAD.encode(payload);
+        encoded.push('.');
+        URL_SAFE_NO_PAD.encode_string(signature, &mut encoded);
+        CsrfToken::from(encoded)
+    }
+
+    /// Verify a signed state, returned by twitch as the `state` parameter, and that it was bound to `binding`.
+    pub fn verify(&self, state: &str, binding: &str) -> Result<SignedState, SignedStateError> {
+        let (payload, signature) = state.split_once('.').ok_or(SignedStateError::Malformed)?;
+        let payload = URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|_| SignedStateError::Malformed)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| SignedStateError::Malformed)?;
+        let mut mac = self.mac();
+        mac.update(&payload);
+        // constant time comparison
+        mac.verify_slice(&signature)
+            .map_err(|_| SignedStateError::InvalidSignature)?;
+
+        let payload: Payload =
+            serde_json::from_slice(&payload).map_err(|_| SignedStateError::Malformed)?;
+        let expires_at = UNIX_EPOCH + Duration::from_secs(payload.exp);
+        if expires_at <= SystemTime::now() {
+            return Err(SignedStateError::Expired);
+        }
+        if !super::constant_time_eq(payload.bind.as_bytes(), hash_binding(binding).as_bytes()) {
+            return Err(SignedStateError::BindingMismatch);
+        }
+        Ok(SignedState {
+            state: payload.state,
+            data: payload.data,
+            expires_at,
+        })
+    }
+}
+
+/// Hash a binding, so that the state doesn't reveal it.
+fn hash_binding(binding: &str) -> String {
+    use sha2::Digest as _;
+
+    URL_SAFE_NO_PAD.encode(Sha256::digest(binding.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scope;
+
+    fn state() -> AuthorizationState {
+        AuthorizationState::new(
+            vec![Scope::ChatRead, Scope::UserReadEmail],
+            url::Url::parse("http://localhost/twitch/register").unwrap(),
+            true,
+        )
+    }
+
+    #[test]
+    fn sign_verify() {
+        let signer = StateSigner::new(*b"secret key", Duration::from_secs(60));
+        let token = signer.sign(state(), "/return?to=here", "browser");
+        assert!(!token.secret().contains(['+', '/', '=']));
+        assert!(!token.secret().contains("browser"));
+
+        let verified = signer.verify(token.secret(), "browser").unwrap();
+        assert_eq!(verified.state, state());
+        assert_eq!(verified.data, "/return?to=here");
+        assert_ne!(
+            signer.sign(state(), "", "browser").secret(),
+            signer.sign(state(), "", "browser").secret()
+        );
+    }
+
+    #[test]
+    fn other_browser() {
+        let signer = StateSigner::new(*b"secret key", Duration::from_secs(60));
+        let token = signer.sign(state(), "", "attacker");
+        assert_eq!(
+            signer.verify(token.secret(), "victim"),
+            Err(SignedStateError::BindingMismatch)
+        );
+        assert_eq!(
+            signer.verify(token.secret(), ""),
+            Err(SignedStateError::BindingMismatch)
+        );
+    }
+
+    #[test]
+    fn tampered() {
+        let signer = StateSigner::new(*b"secret key", Duration::from_secs(60));
+        let token = signer.sign(state(), "data", "browser");
+        let other = StateSigner::new(*b"other key", Duration::from_secs(60));
+        assert_eq!(
+            other.verify(token.secret(), "browser"),
+            Err(SignedStateError::InvalidSignature)
+        );
+
+        let (payload, signature) = token.secret().split_once('.').unwrap();
+        let forged = String::from_utf8(URL_SAFE_NO_PAD.decode(payload).unwrap())
+            .unwrap()
+            .replace("data", "evil");
+        let forged = format!("{}.{signature}", URL_SAFE_NO_PAD.encode(forged));
+        assert_eq!(
+            signer.verify(&forged, "browser"),
+            Err(SignedStateError::InvalidSignature)
+        );
+        assert_eq!(
+            signer.verify("nope", "browser"),
+            Err(SignedStateError::Malformed)
+        );
+    }
+
+    #[test]
+    fn expired() {
+        let signer = StateSigner::new(*b"secret key", Duration::ZERO);
+        let token = signer.sign(state(), "", "browser");
+        assert_eq!(
+            signer.verify(token.secret(), "browser"),
+            Err(SignedStateError::Expired)
+        );
+    }
+}
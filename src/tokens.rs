@@ -1,27 +1,41 @@
 //! Twitch token types
 
 mod app_access_token;
+#[cfg(feature = "validation_cache")]
+mod cache;
 pub mod errors;
+#[cfg(feature = "client")]
+pub mod events;
+#[cfg(feature = "client")]
+mod revoke;
 mod user_token;
 
 #[cfg(feature = "client")]
 use std::future::Future;
 
 pub use app_access_token::AppAccessToken;
+#[cfg(feature = "validation_cache")]
+pub use cache::{CachedValidation, ValidationCache};
+#[cfg(feature = "client")]
+pub use revoke::{revoke_tokens, revoke_tokens_and_refresh_tokens, RevokeOnDrop};
 use twitch_types::{UserId, UserIdRef, UserName, UserNameRef};
 pub use user_token::{
-    DeviceUserTokenBuilder, ImplicitUserTokenBuilder, UserToken, UserTokenBuilder,
+    DeviceUserTokenBuilder, ImplicitUserTokenBuilder, UserToken, UserTokenBuilder, UserTokenUpgrade,
 };
 
 #[cfg(feature = "client")]
-use crate::client::Client;
-use crate::{id::TwitchTokenErrorResponse, scopes::Scope, RequestParseError};
+use crate::client::{BoxFuture, Client, ErasedClient, ErasedClientError};
+use crate::{
+    id::TwitchTokenErrorResponse,
+    scopes::{Scope, ScopeDiff, ScopeSet},
+    RequestParseError,
+};
 
 use errors::ValidationError;
 #[cfg(feature = "client")]
-use errors::{RefreshTokenError, RevokeTokenError};
+use errors::{RefreshTokenError, RevokeTokenError, RevokeTokensError};
 
-use crate::types::{AccessToken, ClientId};
+use crate::types::{AccessToken, ClientId, RefreshTokenRef};
 use serde_derive::Deserialize;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -37,9 +51,12 @@ pub enum BearerTokenType {
 }
 
 /// Trait for twitch tokens to get fields and generalize over [AppAccessToken] and [UserToken]
+///
+/// Can be used as `dyn TwitchToken` to get fields, use [`DynTwitchToken`] to also refresh, validate and revoke.
 pub trait TwitchToken {
     /// Get the type of token.
-    fn token_type() -> BearerTokenType;
+    fn token_type() -> BearerTokenType
+    where Self: Sized;
     /// Client ID associated with the token. Twitch requires this in all helix API calls
     fn client_id(&self) -> &ClientId;
     /// Get the [AccessToken] for authenticating
@@ -54,16 +71,20 @@ pub trait TwitchToken {
     /// println!("token: {}", user_token.token().secret());
     /// ```
     fn token(&self) -> &AccessToken;
+    /// Get the [RefreshToken] for refreshing the token, if there is one
+    fn refresh_token_ref(&self) -> Option<&RefreshTokenRef> { None }
     /// Get the username associated to this token
     fn login(&self) -> Option<&UserNameRef>;
     /// Get the user id associated to this token
     fn user_id(&self) -> Option<&UserIdRef>;
     /// Refresh this token, changing the token to a newer one
+    ///
+    /// The scopes of the token are updated to the scopes twitch returned, the changes are returned as a [`ScopeDiff`].
     #[cfg(feature = "client")]
     fn refresh_token<'a, C>(
         &mut self,
         http_client: &'a C,
-    ) -> impl Future<Output = Result<(), RefreshTokenError<<C as Client>::Error>>> + Send
+    ) -> impl Future<Output = Result<ScopeDiff, RefreshTokenError<<C as Client>::Error>>> + Send
     where
         Self: Sized,
         C: Client;
@@ -89,7 +110,7 @@ pub trait TwitchToken {
         exp.as_secs() == 0 && exp.as_nanos() == 0
     }
     /// Retrieve scopes attached to the token
-    fn scopes(&self) -> &[Scope];
+    fn scopes(&self) -> &ScopeSet;
     /// Validate this token. Should be checked on regularly, according to <https://dev.twitch.tv/docs/authentication/validate-tokens/>
     ///
     /// # Note
@@ -124,6 +145,122 @@ pub trait TwitchToken {
             token.revoke_token(http_client, client_id).await
         }
     }
+
+    /// Revoke the token and its refresh token, if there is one.
+    ///
+    /// Both are revoked even if revoking one fails, the error tells which one failed.
+    #[cfg(feature = "client")]
+    fn revoke_token_and_refresh_token<C>(
+        self,
+        http_client: &C,
+    ) -> impl Future<Output = Result<(), RevokeTokensError<<C as Client>::Error>>> + Send
+    where
+        Self: Sized + Send,
+        C: Client,
+    {
+        async move {
+            let token = self.token();
+            let refresh_token = self.refresh_token_ref();
+            let client_id = self.client_id();
+            let access_token = token.revoke_token(http_client, client_id).await;
+            let refresh_token = match refresh_token {
+                Some(refresh_token) => refresh_token.revoke_token(http_client, client_id).await,
+                None => Ok(()),
+            };
+            match (access_token, refresh_token) {
+                (Ok(()), Ok(())) => Ok(()),
+                (Err(e), Ok(())) => Err(RevokeTokensError::AccessToken(e)),
+                (Ok(()), Err(e)) => Err(RevokeTokensError::RefreshToken(e)),
+                (Err(access_token), Err(refresh_token)) => Err(RevokeTokensError::Both {
+                    access_token,
+                    refresh_token,
+                }),
+            }
+        }
+    }
+}
+
+/// Object safe companion of [`TwitchToken`], to use tokens as `dyn DynTwitchToken`.
+///
+/// Implemented for all tokens, with methods returning boxed futures and taking an [erased client](crate::client::ErasedClient).
+///
+/// # Examples
+///
+/// ```rust
+/// # async move {
+/// # let client = twitch_oauth2::client::DummyClient; stringify!(
+/// let client = reqwest::Client::new();
+/// # );
+/// # fn tokens() -> Vec<Box<dyn DynTwitchToken>> { vec![] }
+/// use twitch_oauth2::{client::ErasedClient, tokens::DynTwitchToken};
+///
+/// let client: &dyn ErasedClient = &client;
+/// let mut tokens: Vec<Box<dyn DynTwitchToken>> = tokens();
+/// for token in &mut tokens {
+///     if token.is_elapsed() {
+///         token.refresh_token_boxed(client).await?;
+///     }
+///     println!("{:?} {:?}", token.bearer_token_type(), token.login());
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// # };
+/// ```
+pub trait DynTwitchToken: TwitchToken + Send + Sync {
+    /// Get the type of token, see [`TwitchToken::token_type`]
+    fn bearer_token_type(&self) -> BearerTokenType;
+
+    /// Refresh this token, see [`TwitchToken::refresh_token`]
+    #[cfg(feature = "client")]
+    fn refresh_token_boxed<'a>(
+        &'a mut self,
+        http_client: &'a dyn ErasedClient,
+    ) -> BoxFuture<'a, Result<ScopeDiff, RefreshTokenError<ErasedClientError>>>;
+
+    /// Validate this token, see [`TwitchToken::validate_token`]
+    #[cfg(feature = "client")]
+    fn validate_token_boxed<'a>(
+        &'a self,
+        http_client: &'a dyn ErasedClient,
+    ) -> BoxFuture<'a, Result<ValidatedToken, ValidationError<ErasedClientError>>>;
+
+    /// Revoke this token, see [`TwitchToken::revoke_token`]
+    #[cfg(feature = "client")]
+    fn revoke_token_boxed<'a>(
+        &'a self,
+        http_client: &'a dyn ErasedClient,
+    ) -> BoxFuture<'a, Result<(), RevokeTokenError<ErasedClientError>>>;
+}
+
+impl<T: TwitchToken + Send + Sync> DynTwitchToken for T {
+    fn bearer_token_type(&self) -> BearerTokenType { T::token_type() }
+
+    #[cfg(feature = "client")]
+    fn refresh_token_boxed<'a>(
+        &'a mut self,
+        http_client: &'a dyn ErasedClient,
+    ) -> BoxFuture<'a, Result<ScopeDiff, RefreshTokenError<ErasedClientError>>> {
+        Box::pin(async move { self.refresh_token(&http_client).await })
+    }
+
+    #[cfg(feature = "client")]
+    fn validate_token_boxed<'a>(
+        &'a self,
+        http_client: &'a dyn ErasedClient,
+    ) -> BoxFuture<'a, Result<ValidatedToken, ValidationError<ErasedClientError>>> {
+        Box::pin(async move { self.token().validate_token(&http_client).await })
+    }
+
+    #[cfg(feature = "client")]
+    fn revoke_token_boxed<'a>(
+        &'a self,
+        http_client: &'a dyn ErasedClient,
+    ) -> BoxFuture<'a, Result<(), RevokeTokenError<ErasedClientError>>> {
+        Box::pin(async move {
+            self.token()
+                .revoke_token(&http_client, self.client_id())
+                .await
+        })
+    }
 }
 
 impl<T: TwitchToken + Send> TwitchToken for Box<T> {
@@ -133,6 +270,8 @@ impl<T: TwitchToken + Send> TwitchToken for Box<T> {
 
     fn token(&self) -> &AccessToken { (**self).token() }
 
+    fn refresh_token_ref(&self) -> Option<&RefreshTokenRef> { (**self).refresh_token_ref() }
+
     fn login(&self) -> Option<&UserNameRef> { (**self).login() }
 
     fn user_id(&self) -> Option<&UserIdRef> { (**self).user_id() }
@@ -141,7 +280,7 @@ impl<T: TwitchToken + Send> TwitchToken for Box<T> {
     async fn refresh_token<'a, C>(
         &mut self,
         http_client: &'a C,
-    ) -> Result<(), RefreshTokenError<<C as Client>::Error>>
+    ) -> Result<ScopeDiff, RefreshTokenError<<C as Client>::Error>>
     where
         Self: Sized,
         C: Client,
@@ -151,7 +290,7 @@ impl<T: TwitchToken + Send> TwitchToken for Box<T> {
 
     fn expires_in(&self) -> std::time::Duration { (**self).expires_in() }
 
-    fn scopes(&self) -> &[Scope] { (**self).scopes() }
+    fn scopes(&self) -> &ScopeSet { (**self).scopes() }
 }
 
 /// Token validation returned from `https://id.twitch.tv/oauth2/validate`
@@ -166,10 +305,13 @@ pub struct ValidatedToken {
     /// User ID associated with the token
     pub user_id: Option<UserId>,
     /// Scopes attached to the token.
-    pub scopes: Option<Vec<Scope>>,
+    pub scopes: Option<ScopeSet>,
     /// Lifetime of the token
     #[serde(deserialize_with = "expires_in")]
     pub expires_in: Option<std::time::Duration>,
+    /// Rate limit of `id.twitch.tv` from the headers of the response, if it was created with [`ValidatedToken::from_response`]
+    #[serde(skip)]
+    pub rate_limit: Option<crate::id::RateLimit>,
 }
 
 fn expires_in<'a, D: serde::de::Deserializer<'a>>(
@@ -184,6 +326,16 @@ fn expires_in<'a, D: serde::de::Deserializer<'a>>(
     }
 }
 
+/// Replace the scopes of a token with the scopes twitch returned, returning the changes.
+///
+/// No scopes means the token has no scopes.
+pub(crate) fn replace_scopes(scopes: &mut ScopeSet, new: Option<&ScopeSet>) -> ScopeDiff {
+    let new = new.cloned().unwrap_or_default();
+    let diff = scopes.diff(&new);
+    *scopes = new;
+    diff
+}
+
 impl ValidatedToken {
     /// Assemble a a validated token from a response.
     ///
@@ -191,8 +343,11 @@ impl ValidatedToken {
     pub fn from_response<B: AsRef<[u8]>>(
         response: &http::Response<B>,
     ) -> Result<ValidatedToken, ValidationError<std::convert::Infallible>> {
-        match crate::parse_response(response) {
-            Ok(ok) => Ok(ok),
+        match crate::parse_response::<ValidatedToken, _>(response) {
+            Ok(mut validated) => {
+                validated.rate_limit = crate::id::RateLimit::from_response(response);
+                Ok(validated)
+            }
             Err(err) => match err {
                 RequestParseError::TwitchError(TwitchTokenErrorResponse { status, .. })
                     if status == http::StatusCode::UNAUTHORIZED =>
@@ -244,6 +399,32 @@ mod tests {
         let response = http::Response::builder().status(200).body(body).unwrap();
         let token = ValidatedToken::from_response(&response).unwrap();
         assert!(token.expires_in.is_none());
+        assert!(token.rate_limit.is_none());
+    }
+
+    #[test]
+    fn validated_rate_limit() {
+        let body = br#"{"client_id":"clientid","scopes":[],"expires_in":3600}"#;
+        let response = http::Response::builder()
+            .status(200)
+            .header("Ratelimit-Limit", "800")
+            .header("Ratelimit-Remaining", "799")
+            .header("Ratelimit-Reset", "1700000000")
+            .body(body)
+            .unwrap();
+        let token = ValidatedToken::from_response(&response).unwrap();
+        assert_eq!(token.rate_limit.unwrap().remaining, 799);
+
+        let body = br#"{"access_token":"token","expires_in":3600,"token_type":"bearer"}"#;
+        let response = http::Response::builder()
+            .status(200)
+            .header("Ratelimit-Limit", "800")
+            .header("Ratelimit-Remaining", "0")
+            .header("Ratelimit-Reset", "1700000000")
+            .body(body)
+            .unwrap();
+        let token = crate::id::TwitchTokenResponse::from_response(&response).unwrap();
+        assert!(token.rate_limit.unwrap().is_exhausted());
     }
 
     #[test]
@@ -258,4 +439,65 @@ mod tests {
         let error = ValidatedToken::from_response(&response).unwrap_err();
         assert!(matches!(error, ValidationError::RequestParseError(_)))
     }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn dyn_token() {
+        use super::{BearerTokenType, DynTwitchToken};
+        use crate::client::{Client, ErasedClient};
+        use crate::{AccessToken, AppAccessToken, UserToken};
+
+        #[derive(Debug, thiserror::Error)]
+        #[error("no connection")]
+        struct NoConnection;
+
+        impl Client for NoConnection {
+            type Error = NoConnection;
+
+            fn req(
+                &self,
+                _: http::Request<Vec<u8>>,
+            ) -> impl std::future::Future<Output = Result<http::Response<Vec<u8>>, Self::Error>>
+                   + Send
+                   + use<> {
+                std::future::ready(Err(NoConnection))
+            }
+        }
+
+        let tokens: Vec<Box<dyn DynTwitchToken>> = vec![
+            Box::new(UserToken::from_existing_unchecked(
+                AccessToken::from("user"),
+                None,
+                "clientid",
+                None,
+                "justintv".into(),
+                "1337".into(),
+                None,
+                None,
+            )),
+            Box::new(AppAccessToken::from_existing_unchecked(
+                AccessToken::from("app"),
+                None,
+                "clientid",
+                "secret",
+                None,
+                None,
+            )),
+        ];
+        assert_eq!(tokens[0].bearer_token_type(), BearerTokenType::UserToken);
+        assert_eq!(
+            tokens[1].bearer_token_type(),
+            BearerTokenType::AppAccessToken
+        );
+        assert_eq!(tokens[0].login().unwrap().as_str(), "justintv");
+
+        let client: &dyn ErasedClient = &NoConnection;
+        for token in &tokens {
+            let Err(ValidationError::Request(error)) = token.validate_token_boxed(client).await
+            else {
+                panic!("expected request error");
+            };
+            assert!(error.downcast_ref::<NoConnection>().is_some());
+        }
+    }
 }
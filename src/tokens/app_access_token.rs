@@ -12,10 +12,10 @@ use super::errors::{AppAccessTokenError, ValidationError};
 use crate::client::Client;
 #[cfg(feature = "client")]
 use crate::tokens::errors::RefreshTokenError;
-use crate::tokens::{Scope, TwitchToken};
+use crate::tokens::{Scope, ScopeDiff, ScopeSet, TwitchToken};
 use crate::{
-    types::{AccessToken, ClientId, ClientSecret, RefreshToken},
-    ClientIdRef, ClientSecretRef,
+    types::{AccessToken, ClientId, ClientSecret, RefreshToken, RefreshTokenRef},
+    ClientIdRef, ClientSecretRef, ValidatedToken,
 };
 
 /// An App Access Token from the [OAuth client credentials flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#client-credentials-grant-flow)
@@ -36,7 +36,7 @@ pub struct AppAccessToken {
     struct_created: Instant,
     client_id: ClientId,
     client_secret: ClientSecret,
-    scopes: Vec<Scope>,
+    scopes: ScopeSet,
 }
 
 impl std::fmt::Debug for AppAccessToken {
@@ -59,31 +59,65 @@ impl TwitchToken for AppAccessToken {
 
     fn token(&self) -> &AccessToken { &self.access_token }
 
+    fn refresh_token_ref(&self) -> Option<&RefreshTokenRef> { self.refresh_token.as_deref() }
+
     fn login(&self) -> Option<&UserNameRef> { None }
 
     fn user_id(&self) -> Option<&UserIdRef> { None }
 
     #[cfg(feature = "client")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "refresh_app_access_token",
+            skip_all,
+            fields(client_id = %self.client_id, scopes = %self.scopes),
+            err(level = "warn", Display)
+        )
+    )]
     async fn refresh_token<'a, C>(
         &mut self,
         http_client: &'a C,
-    ) -> Result<(), RefreshTokenError<<C as Client>::Error>>
+    ) -> Result<ScopeDiff, RefreshTokenError<<C as Client>::Error>>
     where
         C: Client,
     {
-        let (access_token, expires_in, refresh_token) =
-            if let Some(token) = self.refresh_token.take() {
+        let time_to_expiry = self.expires_in();
+        let start = Instant::now();
+        let result: Result<_, RefreshTokenError<C::Error>> = async {
+            let response = if let Some(token) = self.refresh_token.take() {
                 token
-                    .refresh_token(http_client, &self.client_id, Some(&self.client_secret))
+                    .refresh_token_response(http_client, &self.client_id, Some(&self.client_secret))
                     .await?
             } else {
                 return Err(RefreshTokenError::NoRefreshToken);
             };
-        self.access_token = access_token;
-        self.expires_in = expires_in;
-        self.refresh_token = refresh_token;
-        self.struct_created = Instant::now();
-        Ok(())
+            let expires_in = response
+                .expires_in()
+                .ok_or(RefreshTokenError::NoExpiration)?;
+            let diff = self.update_scopes_from_response(&response);
+            crate::trace::record_token(&self.client_id, None, Some(&self.scopes));
+            self.access_token = response.access_token;
+            self.expires_in = expires_in;
+            self.refresh_token = response.refresh_token;
+            self.struct_created = Instant::now();
+            Ok(diff)
+        }
+        .await;
+        crate::tokens::events::emit(http_client, |events| {
+            events.refreshed(&crate::tokens::events::RefreshEvent {
+                token_type: super::BearerTokenType::AppAccessToken,
+                client_id: &self.client_id,
+                user_id: None,
+                time_to_expiry,
+                elapsed: start.elapsed(),
+                outcome: result
+                    .as_ref()
+                    .map(|_| ())
+                    .map_err(crate::tokens::events::ErrorKind::refresh),
+            })
+        });
+        result
     }
 
     fn expires_in(&self) -> std::time::Duration {
@@ -92,7 +126,7 @@ impl TwitchToken for AppAccessToken {
             .unwrap_or_default()
     }
 
-    fn scopes(&self) -> &[Scope] { self.scopes.as_slice() }
+    fn scopes(&self) -> &ScopeSet { &self.scopes }
 }
 
 impl AppAccessToken {
@@ -109,17 +143,20 @@ impl AppAccessToken {
         refresh_token: impl Into<Option<RefreshToken>>,
         client_id: impl Into<ClientId>,
         client_secret: impl Into<ClientSecret>,
-        scopes: Option<Vec<Scope>>,
+        scopes: Option<ScopeSet>,
         expires_in: Option<std::time::Duration>,
     ) -> AppAccessToken {
+        let client_id = client_id.into();
+        let scopes = scopes.unwrap_or_default();
+        crate::scopes::deprecation::warn_issued(&client_id, None, &scopes);
         AppAccessToken {
             access_token,
             refresh_token: refresh_token.into(),
-            client_id: client_id.into(),
+            client_id,
             client_secret: client_secret.into(),
             expires_in: expires_in.unwrap_or_default(),
             struct_created: Instant::now(),
-            scopes: scopes.unwrap_or_default(),
+            scopes,
         }
     }
 
@@ -168,6 +205,23 @@ impl AppAccessToken {
         )
     }
 
+    /// Update the scopes of this token from a [validation](ValidatedToken), returning the changes.
+    ///
+    /// If the validation has no scopes, the token is assumed to have no scopes.
+    pub fn update_scopes_from_validated(&mut self, validated: &ValidatedToken) -> ScopeDiff {
+        super::replace_scopes(&mut self.scopes, validated.scopes.as_ref())
+    }
+
+    /// Update the scopes of this token from a [token response](crate::id::TwitchTokenResponse), returning the changes.
+    ///
+    /// If the response has no scopes, the token is assumed to have no scopes.
+    pub fn update_scopes_from_response(
+        &mut self,
+        response: &crate::id::TwitchTokenResponse,
+    ) -> ScopeDiff {
+        super::replace_scopes(&mut self.scopes, response.scopes.as_ref())
+    }
+
     /// Generate an app access token via [OAuth client credentials flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#client-credentials-grant-flow)
     ///
     /// # Examples
@@ -190,6 +244,15 @@ impl AppAccessToken {
     /// # Ok::<(), Box<dyn std::error::Error>>(())};
     /// ```
     #[cfg(feature = "client")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "get_app_access_token",
+            skip_all,
+            fields(client_id = %client_id, scopes),
+            err(level = "warn", Display)
+        )
+    )]
     pub async fn get_app_access_token<C>(
         http_client: &C,
         client_id: ClientId,
@@ -201,13 +264,15 @@ impl AppAccessToken {
     {
         let req = Self::get_app_access_token_request(&client_id, &client_secret, scopes);
 
-        let resp = http_client
-            .req(req)
+        let mut resp = crate::trace::send(http_client, req)
             .await
             .map_err(AppAccessTokenError::Request)?;
 
-        let response = crate::id::TwitchTokenResponse::from_response(&resp)?;
+        let response = crate::id::TwitchTokenResponse::from_response(&resp);
+        crate::wipe_bytes(resp.body_mut());
+        let response = response?;
         let app_access = AppAccessToken::from_response(response, client_id, client_secret);
+        crate::trace::record_token(&app_access.client_id, None, Some(&app_access.scopes));
 
         Ok(app_access)
     }
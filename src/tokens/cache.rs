@@ -0,0 +1,384 @@
+//! Caching of token validations, see [`ValidationCache`]
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[cfg(not(all(target_family = "wasm", target_os = "unknown")))]
+use std::time::Instant;
+
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
+use web_time::Instant;
+
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "client")]
+use super::errors::{RevokeTokenError, ValidationError};
+use super::ValidatedToken;
+#[cfg(feature = "client")]
+use crate::client::Client;
+use crate::AccessTokenRef;
+#[cfg(feature = "client")]
+use crate::ClientId;
+
+/// A cache of [token validations](ValidatedToken), to not call `/validate` for every use of a token.
+///
+/// Validations are cached for the time to live, or until the token expires if that is sooner.
+/// Tokens that are not authorized are cached as well, for the [negative time to live](ValidationCache::set_negative_ttl).
+/// Entries are keyed by a SHA-256 hash of the token, so the cache doesn't hold on to the tokens themselves.
+/// At most [`max_len`](ValidationCache::set_max_len) validations are cached, when the cache is full the oldest validation is removed to make room.
+///
+/// A revoked token stays cached until its validation expires, revoke tokens with [`ValidationCache::revoke_token`]
+/// or use [`ValidationCache::invalidate`] after revoking them to remove them from the cache.
+///
+/// Cloning is cheap and the clones share the cache.
+///
+/// # Examples
+///
+/// ```rust
+/// # async move {
+/// # let client = twitch_oauth2::client::DummyClient; stringify!(
+/// let client = reqwest::Client::new();
+/// # );
+/// use std::time::Duration;
+/// use twitch_oauth2::{AccessToken, ValidationCache};
+///
+/// let cache = ValidationCache::new(Duration::from_secs(5 * 60));
+/// let token = AccessToken::from("sometokenherewhichisvalidornot");
+/// // only calls `/validate` the first time
+/// let validated = cache.validate_token(&token, &client).await?;
+/// let validated = cache.validate_token(&token, &client).await?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// # };
+/// ```
+#[derive(Debug, Clone)]
+pub struct ValidationCache {
+    ttl: Duration,
+    negative_ttl: Duration,
+    max_len: usize,
+    entries: Arc<Mutex<Entries>>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    map: HashMap<[u8; 32], Entry>,
+    /// Keys and ids of the validations in insertion order.
+    ///
+    /// Validations that were replaced or removed are left in here until they reach the front.
+    order: VecDeque<([u8; 32], u64)>,
+    next_id: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    id: u64,
+    validated: Option<ValidatedToken>,
+    validated_at: Instant,
+    expires: Instant,
+}
+
+impl Entries {
+    /// Remove the oldest validation
+    fn remove_oldest(&mut self) {
+        while let Some((key, id)) = self.order.pop_front() {
+            if self.map.get(&key).is_some_and(|e| e.id == id) {
+                self.map.remove(&key);
+                break;
+            }
+        }
+    }
+}
+
+/// A cached validation, returned by [`ValidationCache::get`]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum CachedValidation {
+    /// The token is valid
+    Valid(ValidatedToken),
+    /// The token is not authorized
+    NotAuthorized,
+}
+
+fn key(token: &AccessTokenRef) -> [u8; 32] { Sha256::digest(token.as_str().as_bytes()).into() }
+
+impl ValidationCache {
+    /// Create a cache where validations are kept for at most `ttl`, caching at most 10 000 validations
+    ///
+    /// Tokens that are not authorized are kept for 10 seconds, or `ttl` if that is shorter, see [`ValidationCache::set_negative_ttl`]
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            negative_ttl: ttl.min(Duration::from_secs(10)),
+            max_len: 10_000,
+            entries: Arc::default(),
+        }
+    }
+
+    /// Set how long tokens that are not authorized are kept
+    pub fn set_negative_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_ttl = ttl;
+        self
+    }
+
+    /// Set the maximum number of cached validations
+    pub fn set_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len.max(1);
+        self
+    }
+
+    /// Get the time to live of validations
+    pub fn ttl(&self) -> Duration { self.ttl }
+
+    /// Get the time to live of tokens that are not authorized
+    pub fn negative_ttl(&self) -> Duration { self.negative_ttl }
+
+    /// Get the maximum number of cached validations
+    pub fn max_len(&self) -> usize { self.max_len }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Get the cached validation of a token
+    ///
+    /// The [`expires_in`](ValidatedToken::expires_in) of the validation counts down from when the token was validated,
+    /// while the [`rate_limit`](ValidatedToken::rate_limit) is the rate limit at the time of the validation.
+    pub fn get(&self, token: &AccessTokenRef) -> Option<CachedValidation> {
+        let mut entries = self.lock();
+        let key = key(token);
+        let entry = entries.map.get(&key)?;
+        let now = Instant::now();
+        if entry.expires <= now {
+            entries.map.remove(&key);
+            return None;
+        }
+        Some(match &entry.validated {
+            Some(validated) => {
+                let mut validated = validated.clone();
+                let elapsed = now.saturating_duration_since(entry.validated_at);
+                validated.expires_in = validated
+                    .expires_in
+                    .map(|expires_in| expires_in.saturating_sub(elapsed));
+                CachedValidation::Valid(validated)
+            }
+            None => CachedValidation::NotAuthorized,
+        })
+    }
+
+    /// Cache the validation of a token, until the [time to live](ValidationCache::ttl) passes or the token expires
+    pub fn insert(&self, token: &AccessTokenRef, validated: ValidatedToken) {
+        let ttl = validated
+            .expires_in
+            .map_or(self.ttl, |expires_in| expires_in.min(self.ttl));
+        self.insert_entry(token, Some(validated), ttl);
+    }
+
+    /// Cache that a token is not authorized
+    pub fn insert_not_authorized(&self, token: &AccessTokenRef) {
+        self.insert_entry(token, None, self.negative_ttl);
+    }
+
+    /// Remove the validation of a token
+    pub fn invalidate(&self, token: &AccessTokenRef) { self.lock().map.remove(&key(token)); }
+
+    /// Remove all validations
+    pub fn clear(&self) {
+        let mut entries = self.lock();
+        entries.map.clear();
+        entries.order.clear();
+    }
+
+    /// Remove all expired validations
+    ///
+    /// Expired validations are otherwise removed when they are looked up, or when the cache is full.
+    pub fn remove_expired(&self) {
+        let now = Instant::now();
+        let mut entries = self.lock();
+        entries.map.retain(|_, e| e.expires > now);
+        let Entries { map, order, .. } = &mut *entries;
+        order.retain(|(key, id)| map.get(key).is_some_and(|e| e.id == *id));
+    }
+
+    fn insert_entry(
+        &self,
+        token: &AccessTokenRef,
+        validated: Option<ValidatedToken>,
+        ttl: Duration,
+    ) {
+        let key = key(token);
+        let mut entries = self.lock();
+        if entries.map.len() >= self.max_len && !entries.map.contains_key(&key) {
+            entries.remove_oldest();
+        }
+        // replaced and removed validations are only removed from the order when they reach the front, drop them before it grows unbounded
+        if entries.order.len() >= 2 * self.max_len {
+            let Entries { map, order, .. } = &mut *entries;
+            order.retain(|(key, id)| map.get(key).is_some_and(|e| e.id == *id));
+        }
+        let id = entries.next_id;
+        entries.next_id += 1;
+        entries.order.push_back((key, id));
+        let now = Instant::now();
+        entries.map.insert(
+            key,
+            Entry {
+                id,
+                validated,
+                validated_at: now,
+                expires: now + ttl,
+            },
+        );
+    }
+
+    /// Returns the number of cached validations, including expired validations that haven't been removed yet
+    pub fn len(&self) -> usize { self.lock().map.len() }
+
+    /// Returns true if there are no cached validations
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Validate a token, using the cached validation if there is one.
+    ///
+    /// Returns [`ValidationError::NotAuthorized`] if the token is cached as not authorized.
+    /// See [`ValidationCache::get`] for how the fields of cached validations relate to the time of validation.
+    #[cfg(feature = "client")]
+    pub async fn validate_token<C>(
+        &self,
+        token: &AccessTokenRef,
+        http_client: &C,
+    ) -> Result<ValidatedToken, ValidationError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        match self.get(token) {
+            Some(CachedValidation::Valid(validated)) => return Ok(validated),
+            Some(CachedValidation::NotAuthorized) => return Err(ValidationError::NotAuthorized),
+            None => {}
+        }
+        match token.validate_token(http_client).await {
+            Ok(validated) => {
+                self.insert(token, validated.clone());
+                Ok(validated)
+            }
+            Err(ValidationError::NotAuthorized) => {
+                self.insert_not_authorized(token);
+                Err(ValidationError::NotAuthorized)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Revoke a token, and remove its validation from this cache.
+    #[cfg(feature = "client")]
+    pub async fn revoke_token<C>(
+        &self,
+        token: &AccessTokenRef,
+        http_client: &C,
+        client_id: &ClientId,
+    ) -> Result<(), RevokeTokenError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        token.revoke_token(http_client, client_id).await?;
+        self.invalidate(token);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validated(expires_in: Option<Duration>) -> ValidatedToken {
+        ValidatedToken {
+            client_id: "clientid".into(),
+            login: Some("justintv".into()),
+            user_id: Some("1337".into()),
+            scopes: None,
+            expires_in,
+            rate_limit: None,
+        }
+    }
+
+    #[test]
+    fn cache() {
+        let cache = ValidationCache::new(Duration::from_secs(60));
+        let token = AccessTokenRef::from_str("token");
+        assert!(cache.get(token).is_none());
+
+        cache.insert(token, validated(Some(Duration::from_secs(3600))));
+        assert!(matches!(cache.get(token), Some(CachedValidation::Valid(_))));
+        assert!(cache.get(AccessTokenRef::from_str("other")).is_none());
+
+        cache.invalidate(token);
+        assert!(cache.get(token).is_none());
+
+        cache.insert_not_authorized(token);
+        assert!(matches!(
+            cache.get(token),
+            Some(CachedValidation::NotAuthorized)
+        ));
+    }
+
+    #[test]
+    fn cache_expires() {
+        let cache = ValidationCache::new(Duration::from_secs(60)).set_negative_ttl(Duration::ZERO);
+        let token = AccessTokenRef::from_str("token");
+        cache.insert(token, validated(Some(Duration::ZERO)));
+        assert!(cache.get(token).is_none(), "bounded by expires_in");
+        cache.insert_not_authorized(token);
+        assert!(cache.get(token).is_none());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn cache_full() {
+        let cache = ValidationCache::new(Duration::from_secs(60)).set_max_len(2);
+        let tokens = ["a", "b", "c"].map(AccessTokenRef::from_static);
+        for token in tokens {
+            cache.insert(token, validated(None));
+        }
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(tokens[0]).is_none(), "oldest is removed");
+        assert!(cache.get(tokens[1]).is_some());
+        assert!(cache.get(tokens[2]).is_some());
+
+        for _ in 0..10 {
+            cache.insert(tokens[2], validated(None));
+        }
+        assert!(cache.get(tokens[1]).is_some(), "reinserting doesn't evict");
+        assert!(cache.lock().order.len() <= 4);
+    }
+
+    #[test]
+    fn cache_counts_down() {
+        let cache = ValidationCache::new(Duration::from_secs(60));
+        let token = AccessTokenRef::from_str("token");
+        cache.insert(token, validated(Some(Duration::from_secs(3600))));
+        cache.lock().map.values_mut().for_each(|e| {
+            e.validated_at -= Duration::from_secs(10);
+        });
+        let Some(CachedValidation::Valid(validated)) = cache.get(token) else {
+            panic!("token should be cached")
+        };
+        let expires_in = validated.expires_in.unwrap();
+        assert!(expires_in <= Duration::from_secs(3590) && expires_in > Duration::from_secs(3580));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn revoke_invalidates() {
+        let client = crate::client::mock::MockClient::default();
+        let cache = ValidationCache::new(Duration::from_secs(60));
+        let other = ValidationCache::new(Duration::from_secs(60));
+        let token = AccessTokenRef::from_str("revoked token");
+        cache.insert(token, validated(None));
+        other.insert(token, validated(None));
+        cache
+            .revoke_token(token, &client, &"clientid".into())
+            .await
+            .unwrap();
+        assert!(cache.get(token).is_none());
+        assert!(other.get(token).is_some(), "other caches are not shared");
+        assert_eq!(client.uris().len(), 1);
+    }
+}
@@ -1,6 +1,6 @@
 //! Errors
 
-use crate::{AccessToken, RefreshToken};
+use crate::{AccessToken, RefreshToken, UserToken, Validator};
 
 /// General errors for talking with twitch, used in [`AppAccessToken::get_app_access_token`](crate::tokens::AppAccessToken::get_app_access_token)
 #[allow(missing_docs)]
@@ -139,6 +139,25 @@ pub enum RevokeTokenError<RE: std::error::Error + Send + Sync + 'static> {
     RequestError(#[source] RE),
 }
 
+/// Errors for [TwitchToken::revoke_token_and_refresh_token][crate::TwitchToken::revoke_token_and_refresh_token]
+#[derive(thiserror::Error, Debug, displaydoc::Display)]
+#[non_exhaustive]
+#[cfg(feature = "client")]
+pub enum RevokeTokensError<RE: std::error::Error + Send + Sync + 'static> {
+    /// could not revoke access token
+    AccessToken(#[source] RevokeTokenError<RE>),
+    /// could not revoke refresh token
+    RefreshToken(#[source] RevokeTokenError<RE>),
+    /// could not revoke access token: {access_token}, nor refresh token: {refresh_token}
+    Both {
+        /// Error revoking the access token
+        #[source]
+        access_token: RevokeTokenError<RE>,
+        /// Error revoking the refresh token
+        refresh_token: RevokeTokenError<RE>,
+    },
+}
+
 /// Errors for [TwitchToken::refresh_token][crate::TwitchToken::refresh_token]
 #[allow(missing_docs)]
 #[derive(thiserror::Error, Debug, displaydoc::Display)]
@@ -183,6 +202,65 @@ impl<RE: std::error::Error + Send + Sync + 'static> From<CreationError<RE>>
     }
 }
 
+/// Errors for [`UserTokenUpgrade::new`](crate::tokens::UserTokenUpgrade::new)
+#[derive(thiserror::Error, Debug, displaydoc::Display)]
+#[non_exhaustive]
+pub enum UserTokenUpgradeError {
+    /// token already has the scopes needed
+    AlreadySatisfied {
+        /// Token passed to the function
+        token: Box<UserToken>,
+    },
+    /// token can not be upgraded by adding scopes, it is missing {missing}
+    Unsatisfiable {
+        /// Token passed to the function
+        token: Box<UserToken>,
+        /// What the token is missing
+        missing: Validator,
+    },
+}
+
+/// Errors for [`UserTokenUpgrade::get_user_token`](crate::tokens::UserTokenUpgrade::get_user_token)
+#[derive(thiserror::Error, Debug, displaydoc::Display)]
+#[non_exhaustive]
+#[cfg(feature = "client")]
+pub enum UserTokenUpgradeExchangeError<RE: std::error::Error + Send + Sync + 'static> {
+    /// could not exchange the code for a new token
+    Exchange {
+        /// Error exchanging the code
+        #[source]
+        error: UserTokenExchangeError<RE>,
+        /// The token that was being upgraded
+        previous: Box<UserToken>,
+    },
+    /// new token is for another user than the previous token
+    UserMismatch {
+        /// The new token
+        token: Box<UserToken>,
+        /// The token that was being upgraded
+        previous: Box<UserToken>,
+    },
+    /// new token is missing scopes: {missing}
+    MissingScopes {
+        /// What the new token is missing
+        missing: Validator,
+        /// The new token
+        token: Box<UserToken>,
+        /// The token that was being upgraded
+        previous: Box<UserToken>,
+    },
+    /// could not revoke the previous token
+    Revoke {
+        /// Error revoking the previous token
+        #[source]
+        error: RevokeTokenError<RE>,
+        /// The new token
+        token: Box<UserToken>,
+        /// The token that was being upgraded, which is still valid
+        previous: Box<UserToken>,
+    },
+}
+
 /// Errors for [ImplicitUserTokenBuilder::get_user_token][crate::tokens::ImplicitUserTokenBuilder::get_user_token]
 #[derive(thiserror::Error, Debug, displaydoc::Display)]
 #[non_exhaustive]
@@ -0,0 +1,349 @@
+//! Observe the lifecycle of tokens, to collect metrics
+//!
+//! Wrap your client in an [`ObservedClient`] with an observer, it's called for every refresh, validation and revocation of a token
+//! done with the client.
+//! With the `metrics` feature, [`MetricsEvents`] records these with the [metrics](https://docs.rs/metrics) crate.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//! # let client = twitch_oauth2::client::DummyClient; stringify!(
+//! let client = reqwest::Client::new();
+//! # );
+//! use twitch_oauth2::tokens::events::{ObservedClient, RefreshEvent, TokenEvents};
+//!
+//! #[derive(Default)]
+//! struct CountFailures(AtomicUsize);
+//!
+//! impl TokenEvents for CountFailures {
+//!     fn refreshed(&self, event: &RefreshEvent<'_>) {
+//!         if let Err(kind) = event.outcome {
+//!             self.0.fetch_add(1, Ordering::Relaxed);
+//!             eprintln!("refresh of {} failed: {}", event.client_id, kind.as_str());
+//!         }
+//!     }
+//! }
+//!
+//! let client = ObservedClient::new(client, CountFailures::default());
+//! // use `client` to refresh, validate and revoke tokens
+//! ```
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use twitch_types::UserIdRef;
+
+use super::errors::{RefreshTokenError, RevokeTokenError, ValidationError};
+use super::BearerTokenType;
+use crate::{client::Client, ClientIdRef, RequestParseError, ValidatedToken};
+
+/// Observer of token lifecycle events, see [`ObservedClient`]
+///
+/// All methods do nothing by default.
+pub trait TokenEvents: Send + Sync {
+    /// Called when a [token](crate::TwitchToken) has been refreshed or failed to refresh
+    fn refreshed(&self, event: &RefreshEvent<'_>) { let _ = event; }
+
+    /// Called when a token has been validated or failed to validate
+    fn validated(&self, event: &ValidationEvent<'_>) { let _ = event; }
+
+    /// Called when a token has been revoked or failed to revoke
+    fn revoked(&self, event: &RevocationEvent<'_>) { let _ = event; }
+}
+
+/// A [`Client`] reporting the lifecycle events of tokens used with it to an observer.
+///
+/// Cloning is cheap and the clones share the observer.
+pub struct ObservedClient<C> {
+    client: C,
+    events: Arc<dyn TokenEvents>,
+}
+
+impl<C: Client> ObservedClient<C> {
+    /// Wrap a client, reporting events to `events`
+    pub fn new(client: C, events: impl TokenEvents + 'static) -> Self {
+        Self {
+            client,
+            events: Arc::new(events),
+        }
+    }
+
+    /// Get the wrapped client
+    pub fn client(&self) -> &C { &self.client }
+}
+
+impl<C: Clone> Clone for ObservedClient<C> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            events: self.events.clone(),
+        }
+    }
+}
+
+impl<C: std::fmt::Debug> std::fmt::Debug for ObservedClient<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ObservedClient")
+            .field("client", &self.client)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C: Client> Client for ObservedClient<C> {
+    type Error = C::Error;
+
+    fn req(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> impl Future<Output = Result<http::Response<Vec<u8>>, Self::Error>> + Send + use<C> {
+        self.client.req(request)
+    }
+
+    fn token_events(&self) -> Option<&dyn TokenEvents> { Some(&*self.events) }
+}
+
+/// Call the observer of the client, if there is one.
+pub(crate) fn emit<C: Client>(client: &C, f: impl FnOnce(&dyn TokenEvents)) {
+    if let Some(events) = client.token_events() {
+        f(events);
+    }
+}
+
+/// A refresh of a token, see [`TokenEvents::refreshed`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct RefreshEvent<'a> {
+    /// Type of the token
+    pub token_type: BearerTokenType,
+    /// Client id of the token
+    pub client_id: &'a ClientIdRef,
+    /// User id of the token, if it's a user token
+    pub user_id: Option<&'a UserIdRef>,
+    /// Time left until the token expired when it was refreshed, zero if it had already expired and [`Duration::MAX`] if it never expires
+    pub time_to_expiry: Duration,
+    /// Time it took to refresh the token
+    pub elapsed: Duration,
+    /// Whether the refresh succeeded
+    pub outcome: Result<(), ErrorKind>,
+}
+
+/// A validation of a token, see [`TokenEvents::validated`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ValidationEvent<'a> {
+    /// Time it took to validate the token
+    pub elapsed: Duration,
+    /// The validated token, or why the validation failed
+    pub outcome: Result<&'a ValidatedToken, ErrorKind>,
+}
+
+/// A revocation of a token, see [`TokenEvents::revoked`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct RevocationEvent<'a> {
+    /// Client id the token was revoked for
+    pub client_id: &'a ClientIdRef,
+    /// Time it took to revoke the token
+    pub elapsed: Duration,
+    /// Whether the revocation succeeded
+    pub outcome: Result<(), ErrorKind>,
+}
+
+/// Kind of a failure, without the details of the error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The request failed
+    Request,
+    /// The token is not authorized
+    NotAuthorized,
+    /// Twitch returned an error
+    Twitch,
+    /// The response could not be parsed
+    Parse,
+    /// The token is of the wrong type
+    InvalidToken,
+    /// There is no refresh token
+    NoRefreshToken,
+    /// There is no client secret
+    NoClientSecret,
+    /// The new token has no expiration
+    NoExpiration,
+    /// Twitch rate limited the request
+    RateLimited,
+}
+
+impl ErrorKind {
+    /// Get the kind as a `snake_case` string, for use as a label
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Request => "request",
+            ErrorKind::NotAuthorized => "not_authorized",
+            ErrorKind::Twitch => "twitch",
+            ErrorKind::Parse => "parse",
+            ErrorKind::InvalidToken => "invalid_token",
+            ErrorKind::NoRefreshToken => "no_refresh_token",
+            ErrorKind::NoClientSecret => "no_client_secret",
+            ErrorKind::NoExpiration => "no_expiration",
+            ErrorKind::RateLimited => "rate_limited",
+        }
+    }
+
+    fn parse(error: &RequestParseError) -> Self {
+        match error {
+            RequestParseError::TwitchError(_) => ErrorKind::Twitch,
+            RequestParseError::RateLimited(_) => ErrorKind::RateLimited,
+            _ => ErrorKind::Parse,
+        }
+    }
+
+    pub(crate) fn refresh<RE: std::error::Error + Send + Sync + 'static>(
+        error: &RefreshTokenError<RE>,
+    ) -> Self {
+        match error {
+            RefreshTokenError::RequestError(_) => ErrorKind::Request,
+            RefreshTokenError::RequestParseError(e) => Self::parse(e),
+            RefreshTokenError::NoClientSecretFound => ErrorKind::NoClientSecret,
+            RefreshTokenError::NoRefreshToken => ErrorKind::NoRefreshToken,
+            RefreshTokenError::NoExpiration => ErrorKind::NoExpiration,
+        }
+    }
+
+    pub(crate) fn validation<RE: std::error::Error + Send + Sync + 'static>(
+        error: &ValidationError<RE>,
+    ) -> Self {
+        match error {
+            ValidationError::NotAuthorized => ErrorKind::NotAuthorized,
+            ValidationError::RequestParseError(e) => Self::parse(e),
+            ValidationError::Request(_) => ErrorKind::Request,
+            ValidationError::InvalidToken(_) => ErrorKind::InvalidToken,
+        }
+    }
+
+    pub(crate) fn revoke<RE: std::error::Error + Send + Sync + 'static>(
+        error: &RevokeTokenError<RE>,
+    ) -> Self {
+        match error {
+            RevokeTokenError::RequestParseError(e) => Self::parse(e),
+            RevokeTokenError::RequestError(_) => ErrorKind::Request,
+        }
+    }
+}
+
+/// Records token lifecycle events with the [metrics](https://docs.rs/metrics) crate
+///
+/// | Metric | Type | Labels |
+/// |--------|------|--------|
+/// | `twitch_oauth2_refreshes_total` | counter | `token_type`, `outcome` |
+/// | `twitch_oauth2_refresh_duration_seconds` | histogram | `token_type` |
+/// | `twitch_oauth2_refresh_time_to_expiry_seconds` | histogram | `token_type` |
+/// | `twitch_oauth2_validations_total` | counter | `outcome` |
+/// | `twitch_oauth2_validation_duration_seconds` | histogram | |
+/// | `twitch_oauth2_revocations_total` | counter | `outcome` |
+/// | `twitch_oauth2_revocation_duration_seconds` | histogram | |
+///
+/// `outcome` is `ok` or the [kind](ErrorKind::as_str) of the failure.
+///
+/// ```rust
+/// # let client = twitch_oauth2::client::DummyClient; stringify!(
+/// let client = reqwest::Client::new();
+/// # );
+/// use twitch_oauth2::tokens::events::{MetricsEvents, ObservedClient};
+///
+/// let client = ObservedClient::new(client, MetricsEvents);
+/// ```
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsEvents;
+
+#[cfg(feature = "metrics")]
+fn outcome<T>(outcome: &Result<T, ErrorKind>) -> &'static str {
+    match outcome {
+        Ok(_) => "ok",
+        Err(kind) => kind.as_str(),
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl TokenEvents for MetricsEvents {
+    fn refreshed(&self, event: &RefreshEvent<'_>) {
+        let token_type = match event.token_type {
+            BearerTokenType::UserToken => "user_token",
+            BearerTokenType::AppAccessToken => "app_access_token",
+        };
+        metrics::counter!(
+            "twitch_oauth2_refreshes_total",
+            "token_type" => token_type,
+            "outcome" => outcome(&event.outcome),
+        )
+        .increment(1);
+        metrics::histogram!("twitch_oauth2_refresh_duration_seconds", "token_type" => token_type)
+            .record(event.elapsed.as_secs_f64());
+        metrics::histogram!("twitch_oauth2_refresh_time_to_expiry_seconds", "token_type" => token_type)
+            .record(event.time_to_expiry.as_secs_f64());
+    }
+
+    fn validated(&self, event: &ValidationEvent<'_>) {
+        metrics::counter!("twitch_oauth2_validations_total", "outcome" => outcome(&event.outcome))
+            .increment(1);
+        metrics::histogram!("twitch_oauth2_validation_duration_seconds")
+            .record(event.elapsed.as_secs_f64());
+    }
+
+    fn revoked(&self, event: &RevocationEvent<'_>) {
+        metrics::counter!("twitch_oauth2_revocations_total", "outcome" => outcome(&event.outcome))
+            .increment(1);
+        metrics::histogram!("twitch_oauth2_revocation_duration_seconds")
+            .record(event.elapsed.as_secs_f64());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::mock::{user_token, MockClient};
+    use crate::TwitchToken;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Record(Mutex<Vec<(&'static str, Result<(), ErrorKind>)>>);
+
+    impl TokenEvents for Arc<Record> {
+        fn refreshed(&self, event: &RefreshEvent<'_>) {
+            assert_eq!(event.user_id.unwrap().as_str(), "1337");
+            self.0.lock().unwrap().push(("refresh", event.outcome));
+        }
+
+        fn revoked(&self, event: &RevocationEvent<'_>) {
+            self.0.lock().unwrap().push(("revoke", event.outcome));
+        }
+    }
+
+    #[tokio::test]
+    async fn events() {
+        let record = Arc::new(Record::default());
+        let client =
+            MockClient::default().respond(400, r#"{"status":400,"message":"Invalid token"}"#);
+        let mut token = user_token("clientid");
+        assert!(token.refresh_token(&client).await.is_err());
+
+        let client = ObservedClient::new(client, record.clone());
+        assert!(token.refresh_token(&client).await.is_err());
+        assert!(token
+            .token()
+            .revoke_token(&client, token.client_id())
+            .await
+            .is_err());
+
+        assert_eq!(
+            *record.0.lock().unwrap(),
+            [
+                ("refresh", Err(ErrorKind::NoRefreshToken)),
+                ("revoke", Err(ErrorKind::Twitch)),
+            ],
+            "only events of the observed client are reported"
+        );
+    }
+}
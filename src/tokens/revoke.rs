@@ -0,0 +1,289 @@
+use super::errors::{RevokeTokenError, RevokeTokensError};
+use super::TwitchToken;
+use crate::client::{BoxFuture, Client};
+use crate::{AccessTokenRef, ClientId};
+
+type Spawner = Box<dyn Fn(BoxFuture<'static, ()>) + Send + Sync>;
+
+/// Guard revoking a token when it's no longer used, for short-lived tokens in tests and one-shot jobs.
+///
+/// Revoke the token and its refresh token with [`RevokeOnDrop::close`]. If the guard is dropped instead, the revocation is
+/// spawned with the [spawner](RevokeOnDrop::set_spawner). Without a spawner the token is not revoked,
+/// and a warning is logged with the `tracing` feature. Errors revoking on drop are ignored.
+///
+/// The guard dereferences to the token.
+///
+/// # Examples
+///
+/// ```rust
+/// # async move {
+/// # let client = twitch_oauth2::client::DummyClient; stringify!(
+/// let client = reqwest::Client::new();
+/// # );
+/// use twitch_oauth2::{tokens::RevokeOnDrop, AppAccessToken, TwitchToken};
+///
+/// let token = AppAccessToken::get_app_access_token(
+///     &client,
+///     "my_client_id".into(),
+///     "my_client_secret".into(),
+///     vec![],
+/// )
+/// .await?;
+/// let token = RevokeOnDrop::new(token, client).set_spawner(|fut| {
+///     tokio::spawn(fut);
+/// });
+/// println!("{:?}", token.expires_in());
+/// token.close().await?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// # };
+/// ```
+pub struct RevokeOnDrop<T, C>
+where
+    T: TwitchToken + Send + 'static,
+    C: Client + 'static, {
+    inner: Option<(T, C)>,
+    spawner: Option<Spawner>,
+}
+
+impl<T, C> RevokeOnDrop<T, C>
+where
+    T: TwitchToken + Send + 'static,
+    C: Client + 'static,
+{
+    /// Guard a token, revoking it with `client`
+    pub fn new(token: T, client: C) -> Self {
+        Self {
+            inner: Some((token, client)),
+            spawner: None,
+        }
+    }
+
+    /// Set the spawner used to revoke the token when the guard is dropped, like `tokio::spawn`
+    pub fn set_spawner(
+        mut self,
+        spawner: impl Fn(BoxFuture<'static, ()>) + Send + Sync + 'static,
+    ) -> Self {
+        self.spawner = Some(Box::new(spawner));
+        self
+    }
+
+    /// Get the client used to revoke the token
+    pub fn client(&self) -> &C { &self.inner().1 }
+
+    /// Revoke the token and its refresh token, see [`TwitchToken::revoke_token_and_refresh_token`]
+    pub async fn close(mut self) -> Result<(), RevokeTokensError<C::Error>> {
+        let (token, client) = self.inner.take().expect("only taken when consumed");
+        token.revoke_token_and_refresh_token(&client).await
+    }
+
+    /// Get the token back, without revoking it
+    pub fn into_inner(mut self) -> T { self.inner.take().expect("only taken when consumed").0 }
+
+    fn inner(&self) -> &(T, C) { self.inner.as_ref().expect("only taken when consumed") }
+}
+
+impl<T, C> std::ops::Deref for RevokeOnDrop<T, C>
+where
+    T: TwitchToken + Send + 'static,
+    C: Client + 'static,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T { &self.inner().0 }
+}
+
+impl<T, C> std::ops::DerefMut for RevokeOnDrop<T, C>
+where
+    T: TwitchToken + Send + 'static,
+    C: Client + 'static,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner.as_mut().expect("only taken when consumed").0
+    }
+}
+
+impl<T, C> std::fmt::Debug for RevokeOnDrop<T, C>
+where
+    T: TwitchToken + Send + std::fmt::Debug + 'static,
+    C: Client + 'static,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RevokeOnDrop")
+            .field("token", &self.inner().0)
+            .field("spawner", &self.spawner.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, C> Drop for RevokeOnDrop<T, C>
+where
+    T: TwitchToken + Send + 'static,
+    C: Client + 'static,
+{
+    fn drop(&mut self) {
+        let Some((token, client)) = self.inner.take() else {
+            return;
+        };
+        match &self.spawner {
+            Some(spawner) => spawner(Box::pin(async move {
+                let _ = token.revoke_token_and_refresh_token(&client).await;
+            })),
+            None => crate::trace::not_revoked(token.client_id()),
+        }
+    }
+}
+
+/// Revoke many access tokens, with at most `concurrency` revocations at a time.
+///
+/// Refresh tokens are not revoked, use [`revoke_tokens_and_refresh_tokens`] to revoke them too.
+/// Returns the result of every revocation, in the order of `tokens`. Use this to revoke all tokens of a user when they disconnect,
+/// e.g. with [`Sessions::remove_user`](crate::web::Sessions::remove_user) when using the `web` handlers.
+///
+/// # Examples
+///
+/// ```rust
+/// # async move {
+/// # let client = twitch_oauth2::client::DummyClient; stringify!(
+/// let client = reqwest::Client::new();
+/// # );
+/// # fn tokens_of_user() -> Vec<twitch_oauth2::UserToken> { vec![] }
+/// use twitch_oauth2::{tokens::revoke_tokens, TwitchToken};
+///
+/// let tokens = tokens_of_user();
+/// let results = revoke_tokens(
+///     &client,
+///     &"myclientid".into(),
+///     tokens.iter().map(|t| t.token()),
+///     4,
+/// )
+/// .await;
+/// for (token, result) in tokens.iter().zip(results) {
+///     if let Err(e) = result {
+///         println!("could not revoke token of {}: {e}", token.login);
+///     }
+/// }
+/// # };
+/// ```
+pub async fn revoke_tokens<C, T>(
+    http_client: &C,
+    client_id: &ClientId,
+    tokens: impl IntoIterator<Item = T>,
+    concurrency: usize,
+) -> Vec<Result<(), RevokeTokenError<C::Error>>>
+where
+    C: Client,
+    T: AsRef<AccessTokenRef>,
+{
+    use futures::StreamExt as _;
+
+    futures::stream::iter(tokens)
+        .map(|token| async move { token.as_ref().revoke_token(http_client, client_id).await })
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+/// Revoke the access and refresh tokens of many tokens, with at most `concurrency` tokens revoked at a time.
+///
+/// Returns the result of every revocation, in the order of `tokens`, see [`TwitchToken::revoke_token_and_refresh_token`].
+pub async fn revoke_tokens_and_refresh_tokens<C, T>(
+    http_client: &C,
+    tokens: impl IntoIterator<Item = T>,
+    concurrency: usize,
+) -> Vec<Result<(), RevokeTokensError<C::Error>>>
+where
+    C: Client,
+    T: TwitchToken + Send,
+{
+    use futures::StreamExt as _;
+
+    futures::stream::iter(tokens)
+        .map(|token| token.revoke_token_and_refresh_token(http_client))
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::mock::{user_token, MockClient};
+    use crate::{AccessToken, UserToken};
+    use std::sync::{Arc, Mutex};
+
+    fn token() -> UserToken { user_token("clientid") }
+
+    #[tokio::test]
+    async fn revoke_on_drop() {
+        let client = MockClient::default();
+        let guard = RevokeOnDrop::new(token(), client.clone());
+        assert_eq!(guard.login.as_str(), "justintv");
+        guard.close().await.unwrap();
+        assert!(client.uris()[0].starts_with("https://id.twitch.tv/oauth2/revoke?"));
+
+        let spawned = Arc::new(Mutex::new(vec![]));
+        let guard = RevokeOnDrop::new(token(), client.clone()).set_spawner({
+            let spawned = spawned.clone();
+            move |fut| spawned.lock().unwrap().push(fut)
+        });
+        drop(guard);
+        let fut = spawned.lock().unwrap().pop().unwrap();
+        fut.await;
+        assert_eq!(client.uris().len(), 2);
+
+        drop(RevokeOnDrop::new(token(), client.clone()));
+        let _token = RevokeOnDrop::new(token(), client.clone())
+            .set_spawner(|_| panic!("not revoked"))
+            .into_inner();
+        assert_eq!(client.uris().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn bulk() {
+        let client = MockClient::default();
+        let tokens = ["a", "b", "c"].map(AccessToken::from);
+        let results = revoke_tokens(&client, &"clientid".into(), &tokens, 2).await;
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(client.uris().len(), 3);
+
+        let client = MockClient::default();
+        let mut with_refresh = token();
+        with_refresh.refresh_token = Some("refresh".into());
+        let results =
+            revoke_tokens_and_refresh_tokens(&client, [token(), with_refresh, token()], 2).await;
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(client.uris().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn revoke_both() {
+        let client = MockClient::default();
+        let mut token = token();
+        token.refresh_token = Some("refresh".into());
+        token.revoke_token_and_refresh_token(&client).await.unwrap();
+        let requests = client.uris();
+        assert_eq!(requests.len(), 2);
+        assert!(!requests[0].contains("token_type_hint"));
+        assert!(requests[1].contains("token=refresh"));
+        assert!(requests[1].contains("token_type_hint=refresh_token"));
+    }
+
+    #[tokio::test]
+    async fn revoke_both_fails() {
+        let body = r#"{"status":400,"message":"Invalid token"}"#;
+        let client = MockClient::default().respond(400, body).respond(400, body);
+        let mut token = token();
+        token.refresh_token = Some("refresh".into());
+        let guard = RevokeOnDrop::new(token, client.clone());
+        let error = guard.close().await.unwrap_err();
+        assert!(matches!(error, RevokeTokensError::Both { .. }));
+        assert_eq!(
+            error.to_string(),
+            "could not revoke access token: could not parse response when revoking token, \
+             nor refresh token: could not parse response when revoking token"
+        );
+        assert_eq!(client.uris().len(), 2);
+    }
+}
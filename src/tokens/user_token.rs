@@ -9,17 +9,18 @@ use web_time::Instant;
 #[cfg(feature = "client")]
 use super::errors::{
     DeviceUserTokenExchangeError, ImplicitUserTokenExchangeError, RefreshTokenError,
-    RetrieveTokenError, UserTokenExchangeError,
+    RetrieveTokenError, UserTokenExchangeError, UserTokenUpgradeExchangeError,
 };
 #[cfg(feature = "client")]
 use crate::client::Client;
 use crate::{
+    state::{AuthorizationState, StateStore},
     tokens::{
-        errors::{CreationError, ValidationError},
-        Scope, TwitchToken,
+        errors::{CreationError, UserTokenUpgradeError, ValidationError},
+        Scope, ScopeDiff, ScopeSet, TwitchToken,
     },
-    types::{AccessToken, ClientId, RefreshToken},
-    ClientSecret, ValidatedToken,
+    types::{AccessToken, ClientId, RefreshToken, RefreshTokenRef},
+    ClientSecret, ValidatedToken, Validator,
 };
 
 #[allow(clippy::too_long_first_doc_paragraph)] // clippy bug - https://github.com/rust-lang/rust-clippy/issues/13315
@@ -44,7 +45,7 @@ pub struct UserToken {
     expires_in: std::time::Duration,
     /// When this struct was created, not when token was created.
     struct_created: Instant,
-    scopes: Vec<Scope>,
+    scopes: ScopeSet,
     /// Token will never expire
     ///
     /// This is only true for old client IDs, like <https://twitchapps.com/tmi> and others
@@ -262,19 +263,22 @@ impl UserToken {
         client_secret: impl Into<Option<ClientSecret>>,
         login: UserName,
         user_id: UserId,
-        scopes: Option<Vec<Scope>>,
+        scopes: Option<ScopeSet>,
         expires_in: Option<std::time::Duration>,
     ) -> UserToken {
+        let client_id = client_id.into();
+        let scopes = scopes.unwrap_or_default();
+        crate::scopes::deprecation::warn_issued(&client_id, Some(&user_id), &scopes);
         UserToken {
             access_token: access_token.into(),
-            client_id: client_id.into(),
+            client_id,
             client_secret: client_secret.into(),
             login,
             user_id,
             refresh_token: refresh_token.into(),
             expires_in: expires_in.unwrap_or(std::time::Duration::MAX),
             struct_created: Instant::now(),
-            scopes: scopes.unwrap_or_default(),
+            scopes,
             never_expiring: expires_in.is_none(),
         }
     }
@@ -293,6 +297,23 @@ impl UserToken {
         )
     }
 
+    /// Update the scopes of this token from a [validation](ValidatedToken), returning the changes.
+    ///
+    /// If the validation has no scopes, the token is assumed to have no scopes.
+    pub fn update_scopes_from_validated(&mut self, validated: &ValidatedToken) -> ScopeDiff {
+        super::replace_scopes(&mut self.scopes, validated.scopes.as_ref())
+    }
+
+    /// Update the scopes of this token from a [token response](crate::id::TwitchTokenResponse), returning the changes.
+    ///
+    /// If the response has no scopes, the token is assumed to have no scopes.
+    pub fn update_scopes_from_response(
+        &mut self,
+        response: &crate::id::TwitchTokenResponse,
+    ) -> ScopeDiff {
+        super::replace_scopes(&mut self.scopes, response.scopes.as_ref())
+    }
+
     #[doc(hidden)]
     /// Returns true if this token is never expiring.
     ///
@@ -309,6 +330,18 @@ impl UserToken {
         UserTokenBuilder::new(client_id, client_secret, redirect_url)
     }
 
+    /// Create a [`UserTokenUpgrade`] to re-authorize this token with the scopes needed to satisfy `validator`
+    ///
+    /// See [`UserTokenUpgrade::new`]
+    pub fn upgrade(
+        self,
+        client_secret: impl Into<ClientSecret>,
+        redirect_url: url::Url,
+        validator: Validator,
+    ) -> Result<UserTokenUpgrade, UserTokenUpgradeError> {
+        UserTokenUpgrade::new(self, client_secret, redirect_url, validator)
+    }
+
     /// Generate a user token from [mock-api](https://github.com/twitchdev/twitch-cli/blob/main/docs/mock-api.md#auth-namespace)
     ///
     /// # Examples
@@ -360,11 +393,13 @@ impl UserToken {
             vec![],
         );
 
-        let resp = http_client
+        let mut resp = http_client
             .req(req)
             .await
             .map_err(UserTokenExchangeError::RequestError)?;
-        let response = crate::id::TwitchTokenResponse::from_response(&resp)?;
+        let response = crate::id::TwitchTokenResponse::from_response(&resp);
+        crate::wipe_bytes(resp.body_mut());
+        let response = response?;
 
         Ok(UserToken::from_existing(
             http_client,
@@ -386,32 +421,70 @@ impl TwitchToken for UserToken {
 
     fn token(&self) -> &AccessToken { &self.access_token }
 
+    fn refresh_token_ref(&self) -> Option<&RefreshTokenRef> { self.refresh_token.as_deref() }
+
     fn login(&self) -> Option<&UserNameRef> { Some(&self.login) }
 
     fn user_id(&self) -> Option<&UserIdRef> { Some(&self.user_id) }
 
     #[cfg(feature = "client")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "refresh_user_token",
+            skip_all,
+            fields(client_id = %self.client_id, user_id = %self.user_id, scopes = %self.scopes),
+            err(level = "warn", Display)
+        )
+    )]
     async fn refresh_token<'a, C>(
         &mut self,
         http_client: &'a C,
-    ) -> Result<(), RefreshTokenError<<C as Client>::Error>>
+    ) -> Result<ScopeDiff, RefreshTokenError<<C as Client>::Error>>
     where
         Self: Sized,
         C: Client,
     {
-        let (access_token, expires, refresh_token) = if let Some(token) = self.refresh_token.take()
-        {
-            token
-                .refresh_token(http_client, &self.client_id, self.client_secret.as_ref())
-                .await?
-        } else {
-            return Err(RefreshTokenError::NoRefreshToken);
-        };
-        self.access_token = access_token;
-        self.expires_in = expires;
-        self.refresh_token = refresh_token;
-        self.struct_created = Instant::now();
-        Ok(())
+        let time_to_expiry = self.expires_in();
+        let start = Instant::now();
+        let result: Result<_, RefreshTokenError<C::Error>> = async {
+            let response = if let Some(token) = self.refresh_token.take() {
+                token
+                    .refresh_token_response(
+                        http_client,
+                        &self.client_id,
+                        self.client_secret.as_ref(),
+                    )
+                    .await?
+            } else {
+                return Err(RefreshTokenError::NoRefreshToken);
+            };
+            let expires = response
+                .expires_in()
+                .ok_or(RefreshTokenError::NoExpiration)?;
+            let diff = self.update_scopes_from_response(&response);
+            crate::trace::record_token(&self.client_id, Some(&self.user_id), Some(&self.scopes));
+            self.access_token = response.access_token;
+            self.expires_in = expires;
+            self.refresh_token = response.refresh_token;
+            self.struct_created = Instant::now();
+            Ok(diff)
+        }
+        .await;
+        crate::tokens::events::emit(http_client, |events| {
+            events.refreshed(&crate::tokens::events::RefreshEvent {
+                token_type: super::BearerTokenType::UserToken,
+                client_id: &self.client_id,
+                user_id: Some(&self.user_id),
+                time_to_expiry,
+                elapsed: start.elapsed(),
+                outcome: result
+                    .as_ref()
+                    .map(|_| ())
+                    .map_err(crate::tokens::events::ErrorKind::refresh),
+            })
+        });
+        result
     }
 
     fn expires_in(&self) -> std::time::Duration {
@@ -425,7 +498,7 @@ impl TwitchToken for UserToken {
         }
     }
 
-    fn scopes(&self) -> &[Scope] { self.scopes.as_slice() }
+    fn scopes(&self) -> &ScopeSet { &self.scopes }
 }
 
 /// Builder for [OAuth authorization code flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#authorization-code-grant-flow)
@@ -583,7 +656,28 @@ impl UserTokenBuilder {
     ///
     /// First step in the [guide](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#get-the-user-to-authorize-your-app)
     pub fn generate_url(&mut self) -> (url::Url, crate::types::CsrfToken) {
-        let csrf = crate::types::CsrfToken::new_random();
+        self.generate_url_with_csrf(crate::types::CsrfToken::new_random())
+    }
+
+    /// Generate the URL to request a code, with a [signed state](crate::state::StateSigner) embedding the authorization and `data`.
+    ///
+    /// The state is bound to `binding`, a random value to store in the browser. No state needs to be stored on the server,
+    /// rebuild the builder in the callback with [`UserTokenBuilder::from_signed_state`].
+    #[cfg(feature = "signed_state")]
+    pub fn generate_url_signed(
+        &mut self,
+        signer: &crate::state::StateSigner,
+        data: &str,
+        binding: &str,
+    ) -> (url::Url, crate::types::CsrfToken) {
+        let csrf = signer.sign(self.authorization_state(), data, binding);
+        self.generate_url_with_csrf(csrf)
+    }
+
+    fn generate_url_with_csrf(
+        &mut self,
+        csrf: crate::types::CsrfToken,
+    ) -> (url::Url, crate::types::CsrfToken) {
         self.csrf = Some(csrf.clone());
         let mut url = crate::AUTH_URL.clone();
 
@@ -614,10 +708,89 @@ impl UserTokenBuilder {
     #[doc(hidden)]
     pub fn set_csrf(&mut self, csrf: crate::types::CsrfToken) { self.csrf = Some(csrf); }
 
+    /// Get the state of this authorization, to rebuild the builder with [`UserTokenBuilder::from_state`].
+    pub fn authorization_state(&self) -> AuthorizationState {
+        AuthorizationState::new(
+            self.scopes.clone(),
+            self.redirect_url.clone(),
+            self.force_verify,
+        )
+    }
+
+    /// Rebuild a builder from a [stored state](AuthorizationState) and the CSRF token it was stored with.
+    ///
+    /// See [`state`](crate::state) for how to use this with a [`StateStore`].
+    pub fn from_state(
+        client_id: impl Into<ClientId>,
+        client_secret: impl Into<ClientSecret>,
+        csrf: crate::types::CsrfToken,
+        state: AuthorizationState,
+    ) -> UserTokenBuilder {
+        UserTokenBuilder {
+            scopes: state.scopes,
+            csrf: Some(csrf),
+            force_verify: state.force_verify,
+            redirect_url: state.redirect_url,
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+        }
+    }
+
+    /// Generate the URL to request a code, and store the [state](AuthorizationState) in `store`.
+    ///
+    /// See [`UserTokenBuilder::generate_url`] and [`state`](crate::state)
+    pub async fn generate_url_stored<S: StateStore>(
+        &mut self,
+        store: &S,
+    ) -> Result<(url::Url, crate::types::CsrfToken), S::Error> {
+        let (url, csrf) = self.generate_url();
+        store
+            .insert(csrf.clone(), self.authorization_state())
+            .await?;
+        Ok((url, csrf))
+    }
+
+    /// Rebuild a builder from a [signed state](crate::state::StateSigner) created by [`UserTokenBuilder::generate_url_signed`].
+    ///
+    /// `binding` is the value the state was bound to, stored in the browser. Returns the builder and the data embedded in the state.
+    #[cfg(feature = "signed_state")]
+    pub fn from_signed_state(
+        signer: &crate::state::StateSigner,
+        client_id: impl Into<ClientId>,
+        client_secret: impl Into<ClientSecret>,
+        state: &str,
+        binding: &str,
+    ) -> Result<(UserTokenBuilder, String), crate::state::SignedStateError> {
+        let signed = signer.verify(state, binding)?;
+        let builder = Self::from_state(
+            client_id,
+            client_secret,
+            crate::types::CsrfToken::from(state),
+            signed.state,
+        );
+        Ok((builder, signed.data))
+    }
+
+    /// Rebuild a builder from the `state` twitch redirected the user back with, taking it out of `store`.
+    ///
+    /// Returns `None` if the state is unknown or expired. See [`state`](crate::state)
+    pub async fn from_store<S: StateStore>(
+        store: &S,
+        client_id: impl Into<ClientId>,
+        client_secret: impl Into<ClientSecret>,
+        state: &str,
+    ) -> Result<Option<UserTokenBuilder>, S::Error> {
+        let csrf = crate::types::CsrfTokenRef::from_str(state);
+        Ok(store
+            .take(csrf)
+            .await?
+            .map(|stored| Self::from_state(client_id, client_secret, csrf.to_owned(), stored)))
+    }
+
     /// Check if the CSRF is valid
     pub fn csrf_is_valid(&self, csrf: &str) -> bool {
         if let Some(csrf2) = &self.csrf {
-            csrf2.secret() == csrf
+            csrf2.matches(csrf)
         } else {
             false
         }
@@ -686,6 +859,15 @@ impl UserTokenBuilder {
     ///
     /// On failure to authenticate due to wrong redirect url or other errors, twitch redirects the user to `<redirect_url or first defined url in dev console>?error=<error type>&error_description=<description of error>`
     #[cfg(feature = "client")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "get_user_token",
+            skip_all,
+            fields(client_id = %self.client_id, user_id, scopes),
+            err(level = "warn", Display)
+        )
+    )]
     pub async fn get_user_token<C>(
         self,
         http_client: &C,
@@ -702,19 +884,184 @@ impl UserTokenBuilder {
 
         let req = self.get_user_token_request(code);
 
-        let resp = http_client
-            .req(req)
+        let mut resp = crate::trace::send(http_client, req)
             .await
             .map_err(UserTokenExchangeError::RequestError)?;
 
-        let response = crate::id::TwitchTokenResponse::from_response(&resp)?;
+        let response = crate::id::TwitchTokenResponse::from_response(&resp);
+        crate::wipe_bytes(resp.body_mut());
+        let response = response?;
         let validated = response.access_token.validate_token(http_client).await?;
+        crate::trace::record_validated(&validated);
 
         UserToken::from_response(response, validated, self.client_secret)
             .map_err(|e| e.into_other().into())
     }
 }
 
+/// Re-authorization of a [`UserToken`] to gain the scopes required by a [`Validator`]
+///
+/// Uses the [OAuth authorization code flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#authorization-code-grant-flow) with [`UserTokenBuilder`],
+/// requesting the scopes the token already has together with the fewest scopes needed to satisfy the validator.
+/// When the user has authorized the new scopes, the new token is checked against the validator and the previous token is revoked.
+///
+/// # Examples
+///
+/// ```rust
+/// # async move {
+/// use twitch_oauth2::{tokens::UserTokenUpgrade, validator, Scope, UserToken};
+/// use url::Url;
+///
+/// # fn token() -> UserToken { todo!() }
+/// let token: UserToken = token();
+/// let redirect_url = Url::parse("http://localhost/twitch/register")?;
+/// let mut upgrade = UserTokenUpgrade::new(
+///     token,
+///     "myclientsecret",
+///     redirect_url,
+///     validator!(Scope::ChatRead, Scope::UserWriteChat),
+/// )?;
+/// println!("requesting additional scopes: {:?}", upgrade.added_scopes());
+/// let (url, csrf_token) = upgrade.generate_url();
+/// // Make your user navigate to this URL, and extract `state` and `code` when they are redirected back.
+/// # let (state, code) = ("state", "code");
+/// # let client = twitch_oauth2::client::DummyClient; stringify!(
+/// let client = reqwest::Client::builder()
+///     .redirect(reqwest::redirect::Policy::none())
+///     .build()?;
+/// # );
+/// let token = upgrade.get_user_token(&client, state, code).await?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// # };
+/// ```
+pub struct UserTokenUpgrade {
+    builder: UserTokenBuilder,
+    token: UserToken,
+    validator: Validator,
+    added_scopes: Vec<Scope>,
+}
+
+impl UserTokenUpgrade {
+    /// Create a [`UserTokenUpgrade`] for a token that doesn't satisfy `validator`
+    ///
+    /// Fails if the token already satisfies the validator, or if the validator can't be satisfied by adding scopes.
+    ///
+    /// See [`UserTokenBuilder::new`] for notes on the `redirect_url`.
+    pub fn new(
+        token: UserToken,
+        client_secret: impl Into<ClientSecret>,
+        redirect_url: url::Url,
+        validator: Validator,
+    ) -> Result<UserTokenUpgrade, UserTokenUpgradeError> {
+        let added_scopes = match validator.scopes_to_add(token.scopes()) {
+            Some(added) if added.is_empty() => {
+                return Err(UserTokenUpgradeError::AlreadySatisfied {
+                    token: Box::new(token),
+                })
+            }
+            Some(added) => added,
+            None => {
+                let missing = validator
+                    .missing(token.scopes())
+                    .unwrap_or_else(|| validator.clone());
+                return Err(UserTokenUpgradeError::Unsatisfiable {
+                    token: Box::new(token),
+                    missing,
+                });
+            }
+        };
+        let mut scopes = token.scopes().to_vec();
+        scopes.extend(added_scopes.iter().cloned());
+        let builder = UserTokenBuilder::new(token.client_id.clone(), client_secret, redirect_url)
+            .set_scopes(scopes);
+        Ok(UserTokenUpgrade {
+            builder,
+            token,
+            validator,
+            added_scopes,
+        })
+    }
+
+    /// Scopes that are requested in addition to the scopes of the current token
+    pub fn added_scopes(&self) -> &[Scope] { &self.added_scopes }
+
+    /// The validator the new token has to satisfy
+    pub fn validator(&self) -> &Validator { &self.validator }
+
+    /// The token that is being upgraded
+    pub fn token(&self) -> &UserToken { &self.token }
+
+    /// Cancel the upgrade, returning the token that was being upgraded
+    pub fn into_token(self) -> UserToken { self.token }
+
+    /// Enable or disable function to make the user able to switch accounts if needed.
+    pub fn force_verify(mut self, b: bool) -> Self {
+        self.builder = self.builder.force_verify(b);
+        self
+    }
+
+    /// Generate the URL to request a code, requesting the union of the current and added scopes.
+    ///
+    /// See [`UserTokenBuilder::generate_url`]
+    pub fn generate_url(&mut self) -> (url::Url, crate::types::CsrfToken) {
+        self.builder.generate_url()
+    }
+
+    /// Check if the CSRF is valid
+    pub fn csrf_is_valid(&self, csrf: &str) -> bool { self.builder.csrf_is_valid(csrf) }
+
+    /// Exchange the code for a new token, check it against the validator and revoke the previous token.
+    ///
+    /// The new token must be for the same user as the previous token. If any check fails, the previous token is not revoked.
+    #[cfg(feature = "client")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "upgrade_user_token",
+            skip_all,
+            fields(client_id = %self.token.client_id, user_id = %self.token.user_id),
+            err(level = "warn", Display)
+        )
+    )]
+    pub async fn get_user_token<C>(
+        self,
+        http_client: &C,
+        state: &str,
+        code: &str,
+    ) -> Result<UserToken, UserTokenUpgradeExchangeError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        let previous = Box::new(self.token);
+        let token = match self.builder.get_user_token(http_client, state, code).await {
+            Ok(token) => Box::new(token),
+            Err(error) => return Err(UserTokenUpgradeExchangeError::Exchange { error, previous }),
+        };
+        if token.user_id != previous.user_id {
+            return Err(UserTokenUpgradeExchangeError::UserMismatch { token, previous });
+        }
+        if let Some(missing) = self.validator.missing(token.scopes()) {
+            return Err(UserTokenUpgradeExchangeError::MissingScopes {
+                missing,
+                token,
+                previous,
+            });
+        }
+        if let Err(error) = previous
+            .access_token
+            .revoke_token(http_client, &previous.client_id)
+            .await
+        {
+            return Err(UserTokenUpgradeExchangeError::Revoke {
+                error,
+                token,
+                previous,
+            });
+        }
+        Ok(*token)
+    }
+}
+
 /// Builder for [OAuth implicit code flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#implicit-grant-flow)
 ///
 /// See [`UserTokenBuilder`] for the [OAuth authorization code flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#authorization-code-grant-flow) (requires Client Secret, generally more secure)
@@ -794,7 +1141,7 @@ impl ImplicitUserTokenBuilder {
     /// Check if the CSRF is valid
     pub fn csrf_is_valid(&self, csrf: &str) -> bool {
         if let Some(csrf2) = &self.csrf {
-            csrf2.secret() == csrf
+            csrf2.matches(csrf)
         } else {
             false
         }
@@ -891,6 +1238,15 @@ impl ImplicitUserTokenBuilder {
     /// </html>
     /// ```
     #[cfg(feature = "client")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "get_implicit_user_token",
+            skip_all,
+            fields(client_id = %self.client_id, user_id, scopes),
+            err(level = "warn", Display)
+        )
+    )]
     pub async fn get_user_token<C>(
         self,
         http_client: &C,
@@ -907,14 +1263,21 @@ impl ImplicitUserTokenBuilder {
         }
 
         match (access_token, error, error_description) {
-            (Some(access_token), None, None) => UserToken::from_existing(
-                http_client,
-                crate::types::AccessToken::from(access_token),
-                None,
-                None,
-            )
-            .await
-            .map_err(From::from),
+            (Some(access_token), None, None) => {
+                let token = UserToken::from_existing(
+                    http_client,
+                    crate::types::AccessToken::from(access_token),
+                    None,
+                    None,
+                )
+                .await?;
+                crate::trace::record_token(
+                    &token.client_id,
+                    Some(&token.user_id),
+                    Some(&token.scopes),
+                );
+                Ok(token)
+            }
             (_, error, description) => {
                 let (error, description) = (
                     error.map(|s| s.to_string()),
@@ -1007,6 +1370,15 @@ impl DeviceUserTokenBuilder {
     ///
     /// Use [`DeviceCodeResponse::verification_uri`](crate::id::DeviceCodeResponse::verification_uri) to get the URL the user needs to visit.
     #[cfg(feature = "client")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "start_device_code",
+            skip_all,
+            fields(client_id = %self.client_id),
+            err(level = "warn", Display)
+        )
+    )]
     pub async fn start<'s, C>(
         &'s mut self,
         http_client: &C,
@@ -1015,8 +1387,7 @@ impl DeviceUserTokenBuilder {
         C: Client,
     {
         let req = self.get_exchange_device_code_request();
-        let resp = http_client
-            .req(req)
+        let resp = crate::trace::send(http_client, req)
             .await
             .map_err(DeviceUserTokenExchangeError::DeviceExchangeRequestError)?;
         self.parse_exchange_device_code_response(resp)
@@ -1071,6 +1442,15 @@ impl DeviceUserTokenBuilder {
     /// # };
     /// ```
     #[cfg(feature = "client")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "wait_for_device_code",
+            skip_all,
+            fields(client_id = %self.client_id),
+            err(level = "warn", Display)
+        )
+    )]
     pub async fn wait_for_code<C, Fut>(
         &mut self,
         client: &C,
@@ -1133,6 +1513,16 @@ impl DeviceUserTokenBuilder {
     /// # };
     /// ```
     #[cfg(feature = "client")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "finish_device_code",
+            level = "debug",
+            skip_all,
+            fields(client_id = %self.client_id, user_id, scopes),
+            err(level = "debug", Display)
+        )
+    )]
     pub async fn try_finish<C>(
         &self,
         http_client: &C,
@@ -1143,13 +1533,14 @@ impl DeviceUserTokenBuilder {
         let req = self
             .get_user_token_request()
             .ok_or(DeviceUserTokenExchangeError::NoDeviceCode)?;
-        let resp = http_client
-            .req(req)
+        let mut resp = crate::trace::send(http_client, req)
             .await
             .map_err(DeviceUserTokenExchangeError::TokenRequestError)?;
-        let response = crate::id::TwitchTokenResponse::from_response(&resp)
-            .map_err(DeviceUserTokenExchangeError::TokenParseError)?;
+        let response = crate::id::TwitchTokenResponse::from_response(&resp);
+        crate::wipe_bytes(resp.body_mut());
+        let response = response.map_err(DeviceUserTokenExchangeError::TokenParseError)?;
         let validated = response.access_token.validate_token(http_client).await?;
+        crate::trace::record_validated(&validated);
         // FIXME: get rid of the clone
         UserToken::from_response(response, validated, self.client_secret.clone())
             .map_err(|v| v.into_other().into())
@@ -1194,6 +1585,64 @@ mod tests {
         UserToken::from_response(response, validated, None).unwrap();
     }
 
+    #[test]
+    fn update_scopes() {
+        let mut token = UserToken::from_existing_unchecked(
+            AccessToken::from("token"),
+            None,
+            ClientId::from("random_client"),
+            None,
+            UserName::from("twitchdev"),
+            UserId::from("141981764"),
+            Some([Scope::ChatRead, Scope::ChatEdit].into()),
+            None,
+        );
+        let body = br#"
+        {
+            "access_token": "rfx2uswqe8l4g1mkagrvg5tv0ks3",
+            "expires_in": 14124,
+            "refresh_token": "5b93chm6hdve3mycz05zfzatkfdenfspp1h1ar2xxdalen01",
+            "scope": [
+                "chat:read",
+                "user:read:chat"
+            ],
+            "token_type": "bearer"
+          }
+        "#;
+        let response = http::Response::builder().status(200).body(body).unwrap();
+        let response = TwitchTokenResponse::from_response(&response).unwrap();
+        let diff = token.update_scopes_from_response(&response);
+        assert_eq!(diff.added, [Scope::UserReadChat].into());
+        assert_eq!(diff.removed, [Scope::ChatEdit].into());
+        assert_eq!(
+            token.scopes(),
+            &[Scope::ChatRead, Scope::UserReadChat].into()
+        );
+        assert!(token.update_scopes_from_response(&response).is_empty());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn refresh_updates_scopes() {
+        let client = crate::client::mock::MockClient::default().respond(
+            200,
+            br#"{"access_token":"new","expires_in":14124,"refresh_token":"newrefresh","scope":["chat:read","user:read:chat"],"token_type":"bearer"}"#,
+        );
+        let mut token = crate::client::mock::user_token("clientid");
+        token.refresh_token = Some("refresh".into());
+        token.scopes = [Scope::ChatRead, Scope::ChatEdit].into();
+
+        let diff = token.refresh_token(&client).await.unwrap();
+        assert_eq!(diff.added, [Scope::UserReadChat].into());
+        assert_eq!(diff.removed, [Scope::ChatEdit].into());
+        assert_eq!(token.access_token.secret(), "new");
+        assert_eq!(
+            token.scopes(),
+            &[Scope::ChatRead, Scope::UserReadChat].into()
+        );
+        assert!(client.uris()[0].starts_with("https://id.twitch.tv/oauth2/token"));
+    }
+
     #[test]
     fn generate_url() {
         UserTokenBuilder::new(
@@ -1207,6 +1656,48 @@ mod tests {
         .to_string();
     }
 
+    #[test]
+    fn upgrade_url() {
+        let token = UserToken::from_existing_unchecked(
+            AccessToken::from("token"),
+            None,
+            ClientId::from("random_client"),
+            None,
+            UserName::from("twitchdev"),
+            UserId::from("141981764"),
+            Some([Scope::ChatRead].into()),
+            None,
+        );
+        let validator =
+            crate::validator!(Scope::ChatRead, any(Scope::UserWriteChat, Scope::ChatEdit));
+        let mut upgrade = token
+            .upgrade(
+                "random_secret",
+                url::Url::parse("https://localhost").unwrap(),
+                validator,
+            )
+            .unwrap();
+        assert_eq!(upgrade.added_scopes(), &[Scope::UserWriteChat]);
+        let (url, _) = upgrade.generate_url();
+        assert!(url
+            .query_pairs()
+            .any(|(k, v)| k == "scope" && v == "chat:read user:write:chat"));
+
+        let token = upgrade.into_token();
+        let error = token
+            .upgrade(
+                "random_secret",
+                url::Url::parse("https://localhost").unwrap(),
+                crate::validator!(Scope::ChatRead),
+            )
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error,
+            UserTokenUpgradeError::AlreadySatisfied { .. }
+        ));
+    }
+
     #[tokio::test]
     #[ignore]
     #[cfg(feature = "reqwest")]
@@ -1215,7 +1706,7 @@ mod tests {
             ClientId::new(
                 std::env::var("TWITCH_CLIENT_ID").expect("no env:TWITCH_CLIENT_ID provided"),
             ),
-            ClientSecret::new(
+            ClientSecret::from(
                 std::env::var("TWITCH_CLIENT_SECRET")
                     .expect("no env:TWITCH_CLIENT_SECRET provided"),
             ),
@@ -0,0 +1,207 @@
+//! Tracing of requests and tokens, no-ops unless the `tracing` feature is enabled.
+//!
+//! Only ids, scopes, endpoints and outcomes are recorded, never secrets.
+//! Urls are recorded without their query, as it can contain tokens.
+
+#[cfg(all(
+    feature = "tracing",
+    not(all(target_family = "wasm", target_os = "unknown"))
+))]
+use std::time::Instant;
+
+#[cfg(all(
+    feature = "tracing",
+    all(target_family = "wasm", target_os = "unknown")
+))]
+use web_time::Instant;
+
+use crate::client::Client;
+use crate::{ClientIdRef, ScopeSet, ValidatedToken};
+use twitch_types::UserIdRef;
+
+/// Send a request, emitting an event with the endpoint, status and duration.
+pub(crate) async fn send<C: Client>(
+    client: &C,
+    request: http::Request<Vec<u8>>,
+) -> Result<http::Response<Vec<u8>>, C::Error> {
+    #[cfg(feature = "tracing")]
+    {
+        let method = request.method().clone();
+        let endpoint = format!(
+            "{}{}",
+            request.uri().host().unwrap_or_default(),
+            request.uri().path()
+        );
+        let start = Instant::now();
+        let response = client.req(request).await;
+        let elapsed_ms = start.elapsed().as_millis() as u64;
+        match &response {
+            Ok(response) => tracing::debug!(
+                %method,
+                %endpoint,
+                status = response.status().as_u16(),
+                elapsed_ms,
+                "twitch responded"
+            ),
+            Err(error) => tracing::warn!(
+                %method,
+                %endpoint,
+                elapsed_ms,
+                error = error as &dyn std::error::Error,
+                "request to twitch failed"
+            ),
+        }
+        response
+    }
+    #[cfg(not(feature = "tracing"))]
+    client.req(request).await
+}
+
+/// Record the client id, user id and scopes of a token on the current span.
+pub(crate) fn record_token(
+    client_id: &ClientIdRef,
+    user_id: Option<&UserIdRef>,
+    scopes: Option<&ScopeSet>,
+) {
+    #[cfg(feature = "tracing")]
+    {
+        let span = tracing::Span::current();
+        span.record("client_id", client_id.as_str());
+        if let Some(user_id) = user_id {
+            span.record("user_id", user_id.as_str());
+        }
+        if let Some(scopes) = scopes {
+            span.record("scopes", tracing::field::display(scopes));
+        }
+    }
+    #[cfg(not(feature = "tracing"))]
+    let _ = (client_id, user_id, scopes);
+}
+
+/// Warn that a token guard was dropped without revoking the token.
+pub(crate) fn not_revoked(client_id: &ClientIdRef) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        client_id = client_id.as_str(),
+        "RevokeOnDrop dropped without close() or a spawner, the token is not revoked"
+    );
+    #[cfg(not(feature = "tracing"))]
+    let _ = client_id;
+}
+
+/// Record a validated token on the current span.
+pub(crate) fn record_validated(validated: &ValidatedToken) {
+    record_token(
+        &validated.client_id,
+        validated.user_id.as_deref(),
+        validated.scopes.as_ref(),
+    )
+}
+
+#[cfg(all(test, feature = "tracing", feature = "client"))]
+mod tests {
+    use crate::client::mock::MockClient;
+    use std::sync::{Arc, Mutex};
+    use tracing::{field::Field, span};
+
+    /// Collects every recorded field
+    #[derive(Default)]
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    impl tracing::field::Visit for Collect {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    struct Subscriber(Arc<Mutex<Vec<String>>>);
+
+    impl tracing::Subscriber for Subscriber {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool { true }
+
+        fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+            span.record(&mut Collect(self.0.clone()));
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &span::Id, values: &span::Record<'_>) {
+            values.record(&mut Collect(self.0.clone()));
+        }
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) { event.record(&mut Collect(self.0.clone())); }
+
+        fn enter(&self, _: &span::Id) {}
+
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    #[tokio::test]
+    async fn no_secrets() {
+        let fields = Arc::new(Mutex::new(vec![]));
+        let _guard = tracing::subscriber::set_default(Subscriber(fields.clone()));
+
+        let token = crate::AccessToken::from("supersecrettoken");
+        token
+            .revoke_token(&MockClient::default(), &"clientid".into())
+            .await
+            .unwrap();
+
+        let fields = fields.lock().unwrap();
+        assert!(fields.iter().any(|f| f == "client_id=clientid"));
+        assert!(fields.iter().any(|f| f.contains("/oauth2/revoke")));
+        assert!(fields.iter().any(|f| f == "status=200"));
+        assert!(!fields.iter().any(|f| f.contains("supersecrettoken")));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn warn_deprecated_scopes() {
+        let fields = Arc::new(Mutex::new(vec![]));
+        let _guard = tracing::subscriber::set_default(Subscriber(fields.clone()));
+
+        crate::AppAccessToken::from_existing_unchecked(
+            "token".into(),
+            None,
+            "clientid",
+            "secret",
+            Some([crate::Scope::ChatRead].into()),
+            None,
+        );
+        assert!(fields.lock().unwrap().is_empty());
+        crate::AppAccessToken::from_existing_unchecked(
+            "token".into(),
+            None,
+            "clientid",
+            "secret",
+            Some([crate::Scope::ChatRead, crate::Scope::WhispersEdit].into()),
+            None,
+        );
+
+        let fields = fields.lock().unwrap();
+        assert!(fields.iter().any(|f| f == "deprecated=whispers:edit"));
+        assert!(fields
+            .iter()
+            .any(|f| f == "replacements=user:manage:whispers"));
+    }
+
+    #[test]
+    fn warn_not_revoked() {
+        let fields = Arc::new(Mutex::new(vec![]));
+        let _guard = tracing::subscriber::set_default(Subscriber(fields.clone()));
+
+        drop(crate::tokens::RevokeOnDrop::new(
+            crate::client::mock::user_token("clientid"),
+            MockClient::default(),
+        ));
+
+        let fields = fields.lock().unwrap();
+        assert!(fields
+            .iter()
+            .any(|f| f.contains("the token is not revoked")));
+    }
+}
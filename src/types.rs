@@ -9,9 +9,208 @@ use base64::Engine;
 #[aliri_braid::braid(serde)]
 pub struct ClientId;
 
-/// A Client Secret
-#[aliri_braid::braid(display = "owned", debug = "owned", serde)]
-pub struct ClientSecret;
+/// Define a secret and its borrowed form.
+///
+/// The owned type is written out instead of using [`aliri_braid::braid`], so that it can wipe its buffer when dropped with the `zeroize` feature.
+macro_rules! secret {
+    ($(#[$meta:meta])* pub struct $name:ident; pub struct $ref:ident;) => {
+        $(#[$meta])*
+        ///
+        /// With the `zeroize` feature, the secret is wiped from memory when dropped.
+        #[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+        #[repr(transparent)]
+        pub struct $name(String);
+
+        $(#[$meta])*
+        #[aliri_braid::braid_ref(debug = "omit", display = "omit", serde)]
+        pub struct $ref(str);
+
+        impl $name {
+            #[doc = concat!("Constructs a new ", stringify!($name))]
+            #[inline]
+            pub const fn new(raw: String) -> Self { Self(raw) }
+
+            #[doc = concat!("Constructs a new ", stringify!($name), " from a static reference")]
+            #[inline]
+            #[track_caller]
+            pub fn from_static(raw: &'static str) -> Self { $ref::from_static(raw).to_owned() }
+
+            #[doc = concat!("Converts this `", stringify!($name), "` into a [`Box<", stringify!($ref), ">`]")]
+            ///
+            /// This will drop any excess capacity.
+            #[allow(unsafe_code)]
+            #[inline]
+            pub fn into_boxed_ref(self) -> Box<$ref> {
+                let raw = Box::into_raw(self.take().into_boxed_str());
+                // SAFETY: the ref type is `#[repr(transparent)]` around a single `str` field
+                unsafe { Box::from_raw(raw as *mut $ref) }
+            }
+
+            /// Unwraps the underlying [`String`] value
+            #[inline]
+            pub fn take(mut self) -> String { std::mem::take(&mut self.0) }
+        }
+
+        impl $ref {
+            #[doc = concat!("Converts a [`Box<", stringify!($ref), ">`] into a [`", stringify!($name), "`] without copying or allocating")]
+            #[allow(unsafe_code)]
+            #[inline]
+            pub fn into_owned(self: Box<$ref>) -> $name {
+                let raw = Box::into_raw(self);
+                // SAFETY: the ref type is `#[repr(transparent)]` around a single `str` field
+                let boxed = unsafe { Box::from_raw(raw as *mut str) };
+                $name::new(boxed.into())
+            }
+        }
+
+        impl Drop for $name {
+            fn drop(&mut self) { crate::wipe(&mut self.0) }
+        }
+
+        #[cfg(feature = "zeroize")]
+        impl zeroize::ZeroizeOnDrop for $name {}
+
+        impl ToOwned for $ref {
+            type Owned = $name;
+
+            #[inline]
+            fn to_owned(&self) -> $name { $name(self.as_str().to_owned()) }
+        }
+
+        impl std::ops::Deref for $name {
+            type Target = $ref;
+
+            #[inline]
+            fn deref(&self) -> &$ref { $ref::from_str(&self.0) }
+        }
+
+        impl std::borrow::Borrow<$ref> for $name {
+            #[inline]
+            fn borrow(&self) -> &$ref { self }
+        }
+
+        impl std::borrow::Borrow<str> for $name {
+            #[inline]
+            fn borrow(&self) -> &str { &self.0 }
+        }
+
+        impl AsRef<$ref> for $name {
+            #[inline]
+            fn as_ref(&self) -> &$ref { self }
+        }
+
+        impl AsRef<str> for $name {
+            #[inline]
+            fn as_ref(&self) -> &str { &self.0 }
+        }
+
+        impl PartialEq<$ref> for $name {
+            #[inline]
+            fn eq(&self, other: &$ref) -> bool { self.as_str() == other.as_str() }
+        }
+
+        impl PartialEq<$name> for $ref {
+            #[inline]
+            fn eq(&self, other: &$name) -> bool { self.as_str() == other.as_str() }
+        }
+
+        impl PartialEq<&'_ $ref> for $name {
+            #[inline]
+            fn eq(&self, other: &&$ref) -> bool { self.as_str() == other.as_str() }
+        }
+
+        impl PartialEq<$name> for &'_ $ref {
+            #[inline]
+            fn eq(&self, other: &$name) -> bool { self.as_str() == other.as_str() }
+        }
+
+        impl From<String> for $name {
+            #[inline]
+            fn from(s: String) -> Self { Self::new(s) }
+        }
+
+        impl From<&'_ str> for $name {
+            #[inline]
+            fn from(s: &str) -> Self { Self::new(s.to_owned()) }
+        }
+
+        impl From<Box<str>> for $name {
+            #[inline]
+            fn from(s: Box<str>) -> Self { Self::new(s.into()) }
+        }
+
+        impl From<&'_ $ref> for $name {
+            #[inline]
+            fn from(s: &$ref) -> Self { s.to_owned() }
+        }
+
+        impl From<$name> for String {
+            #[inline]
+            fn from(s: $name) -> Self { s.take() }
+        }
+
+        impl From<$name> for Box<$ref> {
+            #[inline]
+            fn from(s: $name) -> Self { s.into_boxed_ref() }
+        }
+
+        impl From<Box<$ref>> for $name {
+            #[inline]
+            fn from(s: Box<$ref>) -> Self { s.into_owned() }
+        }
+
+        impl<'a> From<std::borrow::Cow<'a, $ref>> for $name {
+            #[inline]
+            fn from(s: std::borrow::Cow<'a, $ref>) -> Self { s.into_owned() }
+        }
+
+        impl<'a> From<$name> for std::borrow::Cow<'a, $ref> {
+            #[inline]
+            fn from(s: $name) -> Self { std::borrow::Cow::Owned(s) }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = std::convert::Infallible;
+
+            #[inline]
+            fn from_str(s: &str) -> Result<Self, Self::Err> { Ok(Self::from(s)) }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { fmt::Debug::fmt(&**self, f) }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&**self, f)
+            }
+        }
+
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                String::deserialize(deserializer).map(Self::new)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for Box<$ref> {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                $name::deserialize(deserializer).map($name::into_boxed_ref)
+            }
+        }
+    };
+}
+
+secret! {
+    /// A Client Secret
+    pub struct ClientSecret;
+    pub struct ClientSecretRef;
+}
 
 impl fmt::Debug for ClientSecretRef {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -24,9 +223,11 @@ impl fmt::Display for ClientSecretRef {
     }
 }
 
-/// An Access Token
-#[aliri_braid::braid(display = "owned", debug = "owned", serde)]
-pub struct AccessToken;
+secret! {
+    /// An Access Token
+    pub struct AccessToken;
+    pub struct AccessTokenRef;
+}
 
 impl fmt::Debug for AccessTokenRef {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -39,9 +240,11 @@ impl fmt::Display for AccessTokenRef {
     }
 }
 
-/// A Refresh Token
-#[aliri_braid::braid(display = "owned", debug = "owned", serde)]
-pub struct RefreshToken;
+secret! {
+    /// A Refresh Token
+    pub struct RefreshToken;
+    pub struct RefreshTokenRef;
+}
 
 impl fmt::Debug for RefreshTokenRef {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -54,9 +257,11 @@ impl fmt::Display for RefreshTokenRef {
     }
 }
 
-/// A Csrf Token
-#[aliri_braid::braid(display = "owned", debug = "owned", serde)]
-pub struct CsrfToken;
+secret! {
+    /// A Csrf Token
+    pub struct CsrfToken;
+    pub struct CsrfTokenRef;
+}
 
 impl fmt::Debug for CsrfTokenRef {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -79,7 +284,7 @@ impl CsrfToken {
 
         let mut random_bytes = vec![0u8; len as usize];
         rand::rng().fill(&mut random_bytes);
-        CsrfToken::new(base64::engine::general_purpose::STANDARD.encode(random_bytes))
+        CsrfToken::from(base64::engine::general_purpose::STANDARD.encode(random_bytes))
     }
 }
 
@@ -107,4 +312,34 @@ impl CsrfTokenRef {
     ///
     /// This function is the same as [`CsrfToken::as_str`](CsrfTokenRef::as_str), but has another name for searchability, prefer to use this function.
     pub fn secret(&self) -> &str { self.as_str() }
+
+    /// Check if the `state` returned by twitch matches this token.
+    ///
+    /// The comparison is done in constant time, to not leak the token through timing.
+    pub fn matches(&self, state: &str) -> bool {
+        crate::state::constant_time_eq(self.as_str().as_bytes(), state.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret() {
+        let token = AccessToken::new("secret".to_owned());
+        assert_eq!(token.secret(), "secret");
+        assert_eq!(token, *AccessTokenRef::from_static("secret"));
+        assert_eq!(
+            format!("{token} {token:?}"),
+            "[redacted access token] [redacted access token]"
+        );
+        assert_eq!(serde_json::to_string(&token).unwrap(), r#""secret""#);
+        assert_eq!(
+            serde_json::from_str::<AccessToken>(r#""secret""#).unwrap(),
+            token
+        );
+        let boxed: Box<AccessTokenRef> = token.clone().into();
+        assert_eq!(boxed.into_owned().take(), token.take());
+    }
 }
@@ -0,0 +1,663 @@
+//! Logging in users with twitch in web applications
+//!
+//! [`TwitchAuth`] implements the [OAuth authorization code flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#authorization-code-grant-flow)
+//! for a web server: the login handler redirects the user to twitch, and the callback handler exchanges the code for a [`UserToken`]
+//! and stores it in a [session](Sessions), identified by a cookie.
+//!
+//! The handlers for specific frameworks are in their own modules, enabled by features:
+//!
+//! * `axum`: `web::axum`
+//! * `actix`: `web::actix`
+//!
+//! To authenticate requests to your own APIs with tokens sent by clients, use `web::tower::BearerAuthLayer` with the `tower` feature.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[cfg(not(all(target_family = "wasm", target_os = "unknown")))]
+use std::time::Instant;
+
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
+use web_time::Instant;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use http::StatusCode;
+
+use crate::{
+    client::Client,
+    state::MemoryStateStore,
+    tokens::{
+        errors::{RevokeTokensError, UserTokenExchangeError},
+        UserTokenBuilder,
+    },
+    ClientId, ClientSecret, Scope, TwitchToken, UserToken, Validator,
+};
+use twitch_types::UserIdRef;
+
+#[cfg(feature = "actix")]
+pub mod actix;
+#[cfg(feature = "axum")]
+pub mod axum;
+#[cfg(feature = "tower")]
+pub mod tower;
+
+/// Name of the cookie binding a login to the browser that started it
+const LOGIN_COOKIE: &str = "twitch_login";
+
+/// Configuration and state for logging in users with twitch.
+///
+/// Create one for your application, and share it with the handlers of your framework.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let client = twitch_oauth2::client::DummyClient; stringify!(
+/// let client = reqwest::Client::new();
+/// # );
+/// use twitch_oauth2::{validator, web::TwitchAuth, Scope};
+///
+/// let auth = TwitchAuth::new(
+///     client,
+///     "myclientid",
+///     "myclientsecret",
+///     url::Url::parse("http://localhost:3000/auth/callback")?,
+/// )
+/// .set_validator(validator!(Scope::UserReadEmail, Scope::ChatRead))
+/// .set_login_redirect("/dashboard");
+/// # Ok(()) }
+/// ```
+#[derive(Debug)]
+pub struct TwitchAuth<C> {
+    client: C,
+    client_id: ClientId,
+    client_secret: ClientSecret,
+    redirect_url: url::Url,
+    scopes: Vec<Scope>,
+    validator: Option<Validator>,
+    force_verify: bool,
+    login_redirect: String,
+    states: MemoryStateStore,
+    sessions: Sessions,
+}
+
+impl<C: Client> TwitchAuth<C> {
+    /// Create a new configuration, `redirect_url` is the url of the callback handler.
+    ///
+    /// Logins expire after 10 minutes if the user doesn't return to the callback, at most 10 000 logins are pending at a time.
+    pub fn new(
+        client: C,
+        client_id: impl Into<ClientId>,
+        client_secret: impl Into<ClientSecret>,
+        redirect_url: url::Url,
+    ) -> Self {
+        Self {
+            client,
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            redirect_url,
+            scopes: vec![],
+            validator: None,
+            force_verify: false,
+            login_redirect: "/".to_owned(),
+            states: MemoryStateStore::new(Duration::from_secs(10 * 60)),
+            sessions: Sessions::default(),
+        }
+    }
+
+    /// Set the scopes to request
+    pub fn set_scopes(mut self, scopes: Vec<Scope>) -> Self {
+        self.scopes = scopes;
+        self
+    }
+
+    /// Set a validator the scopes of the user token have to match.
+    ///
+    /// The scopes needed to match the validator are requested together with the [scopes](TwitchAuth::set_scopes).
+    pub fn set_validator(mut self, validator: Validator) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    /// Enable or disable function to make the user able to switch accounts if needed.
+    pub fn force_verify(mut self, b: bool) -> Self {
+        self.force_verify = b;
+        self
+    }
+
+    /// Set where the user is redirected after logging in, defaults to `/`
+    pub fn set_login_redirect(mut self, path: impl Into<String>) -> Self {
+        self.login_redirect = path.into();
+        self
+    }
+
+    /// Set the maximum number of pending logins, when there are more the oldest login is dropped
+    pub fn set_max_pending_logins(mut self, max: usize) -> Self {
+        self.states = self.states.set_max_len(max);
+        self
+    }
+
+    /// Set the sessions to store logged in users in, to share them with the rest of your application.
+    pub fn set_sessions(mut self, sessions: Sessions) -> Self {
+        self.sessions = sessions;
+        self
+    }
+
+    /// Get the sessions of logged in users
+    pub fn sessions(&self) -> &Sessions { &self.sessions }
+
+    /// Get where the user is redirected after logging in
+    pub fn login_redirect(&self) -> &str { &self.login_redirect }
+
+    /// Start a login, returning the url to redirect the user to and the value of the `Set-Cookie` header to send to the user.
+    ///
+    /// The cookie binds the login to the browser, the [callback](TwitchAuth::pending_login) rejects logins started by another browser.
+    pub async fn login(&self) -> (url::Url, String) {
+        let mut scopes = self.scopes.clone();
+        if let Some(add) = self
+            .validator
+            .as_ref()
+            .and_then(|v| v.scopes_to_add(&scopes))
+        {
+            scopes.extend(add);
+        }
+        let mut builder = UserTokenBuilder::new(
+            self.client_id.clone(),
+            self.client_secret.clone(),
+            self.redirect_url.clone(),
+        )
+        .set_scopes(scopes)
+        .force_verify(self.force_verify);
+        let (url, csrf) = match builder.generate_url_stored(&self.states).await {
+            Ok(login) => login,
+            Err(e) => match e {},
+        };
+        let cookie = format!(
+            "{LOGIN_COOKIE}={}; Path={}; Max-Age={}; HttpOnly; SameSite=Lax{}",
+            csrf.secret(),
+            self.redirect_url.path(),
+            self.states.ttl().as_secs(),
+            if self.is_secure() { "; Secure" } else { "" }
+        );
+        (url, cookie)
+    }
+
+    fn is_secure(&self) -> bool { self.redirect_url.scheme() == "https" }
+
+    /// Complete a login from the query and the values of the `Cookie` headers of the callback, returning the user token.
+    ///
+    /// Use [`TwitchAuth::login_session`] to also start a session.
+    pub async fn callback<'a>(
+        &self,
+        query: &CallbackQuery,
+        cookie_headers: impl IntoIterator<Item = &'a str>,
+    ) -> Result<UserToken, CallbackError<C::Error>> {
+        let pending = self.pending_login(query, cookie_headers).await?;
+        self.finish_login(pending).await
+    }
+
+    /// Restore the login from the query and the values of the `Cookie` headers of the callback, without exchanging the code.
+    ///
+    /// The `state` has to match the cookie set by [`TwitchAuth::login`], so that only the browser that started a login can complete it.
+    /// The state can only be restored once. Exchange the code with [`TwitchAuth::finish_login`].
+    pub async fn pending_login<'a>(
+        &self,
+        query: &CallbackQuery,
+        cookie_headers: impl IntoIterator<Item = &'a str>,
+    ) -> Result<PendingLogin, CallbackError<C::Error>> {
+        if let Some(error) = &query.error {
+            return Err(CallbackError::Denied {
+                error: error.clone(),
+                description: query.error_description.clone(),
+            });
+        }
+        let (Some(code), Some(state)) = (&query.code, &query.state) else {
+            return Err(CallbackError::MissingCode);
+        };
+        let bound = cookie_headers
+            .into_iter()
+            .find_map(|h| cookie_value(h, LOGIN_COOKIE))
+            .is_some_and(|cookie| crate::CsrfTokenRef::from_str(cookie).matches(state));
+        if !bound {
+            return Err(CallbackError::StateMismatch);
+        }
+        let Ok(Some(builder)) = UserTokenBuilder::from_store(
+            &self.states,
+            self.client_id.clone(),
+            self.client_secret.clone(),
+            state,
+        )
+        .await
+        else {
+            return Err(CallbackError::UnknownState);
+        };
+        Ok(PendingLogin {
+            builder,
+            code: code.clone(),
+            state: state.clone(),
+        })
+    }
+
+    /// Exchange the code of a [pending login](TwitchAuth::pending_login) for a user token, and check it against the [validator](TwitchAuth::set_validator).
+    pub async fn finish_login(
+        &self,
+        pending: PendingLogin,
+    ) -> Result<UserToken, CallbackError<C::Error>> {
+        let token = pending
+            .builder
+            .get_user_token(&self.client, &pending.state, &pending.code)
+            .await
+            .map_err(CallbackError::Exchange)?;
+        if let Some(missing) = self
+            .validator
+            .as_ref()
+            .and_then(|v| v.missing(token.scopes()))
+        {
+            return Err(CallbackError::MissingScopes { missing });
+        }
+        Ok(token)
+    }
+
+    /// Complete a login from the query and the values of the `Cookie` headers of the callback, and start a session for the user.
+    ///
+    /// Returns the value of the `Set-Cookie` header to send to the user.
+    pub async fn login_session<'a>(
+        &self,
+        query: &CallbackQuery,
+        cookie_headers: impl IntoIterator<Item = &'a str>,
+    ) -> Result<String, CallbackError<C::Error>> {
+        let token = self.callback(query, cookie_headers).await?;
+        Ok(self.start_session(token))
+    }
+
+    /// Start a session for a user, returning the value of the `Set-Cookie` header to send to the user.
+    pub fn start_session(&self, token: UserToken) -> String {
+        let id = self.sessions.insert(token);
+        self.sessions.cookie(&id, self.is_secure())
+    }
+
+    /// End all sessions of a user and revoke their access and refresh tokens, see [`revoke_tokens_and_refresh_tokens`](crate::tokens::revoke_tokens_and_refresh_tokens)
+    ///
+    /// Revokes at most `concurrency` tokens at a time, returns the result of revoking every token.
+    pub async fn logout_everywhere(
+        &self,
+        user_id: &UserIdRef,
+        concurrency: usize,
+    ) -> Vec<Result<(), RevokeTokensError<C::Error>>> {
+        let tokens = self.sessions.remove_user(user_id);
+        crate::tokens::revoke_tokens_and_refresh_tokens(&self.client, tokens, concurrency).await
+    }
+}
+
+/// A login restored from the callback, with the code that has not been exchanged yet.
+#[non_exhaustive]
+pub struct PendingLogin {
+    /// The builder, restored from the state
+    pub builder: UserTokenBuilder,
+    /// The authorization code
+    pub code: String,
+    /// The `state`
+    pub state: String,
+}
+
+impl std::fmt::Debug for PendingLogin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PendingLogin")
+            .field("scopes", &self.builder.authorization_state().scopes)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The query twitch redirects the user back to the callback with
+#[derive(Debug, Clone, Default, serde_derive::Deserialize)]
+#[non_exhaustive]
+pub struct CallbackQuery {
+    /// The authorization code
+    pub code: Option<String>,
+    /// The `state` sent when logging in
+    pub state: Option<String>,
+    /// Error if the user didn't authorize the application
+    pub error: Option<String>,
+    /// Description of the error
+    pub error_description: Option<String>,
+}
+
+/// Errors for [`TwitchAuth::callback`]
+#[derive(thiserror::Error, Debug, displaydoc::Display)]
+#[non_exhaustive]
+pub enum CallbackError<RE: std::error::Error + Send + Sync + 'static> {
+    /// authorization was denied: {error}
+    Denied {
+        /// The error returned by twitch
+        error: String,
+        /// Description of the error
+        description: Option<String>,
+    },
+    /// callback is missing the code or state
+    MissingCode,
+    /// state doesn't match the login cookie, the login was started by another browser
+    StateMismatch,
+    /// state is unknown or expired
+    UnknownState,
+    /// could not exchange the code for a user token
+    Exchange(#[source] UserTokenExchangeError<RE>),
+    /// user token is missing scopes: {missing}
+    MissingScopes {
+        /// Validator for the scopes that are missing
+        missing: Validator,
+    },
+}
+
+impl<RE: std::error::Error + Send + Sync + 'static> CallbackError<RE> {
+    /// Status code to respond with
+    pub fn status(&self) -> StatusCode {
+        match self {
+            CallbackError::Denied { .. } | CallbackError::MissingScopes { .. } => {
+                StatusCode::FORBIDDEN
+            }
+            CallbackError::MissingCode
+            | CallbackError::StateMismatch
+            | CallbackError::UnknownState => StatusCode::BAD_REQUEST,
+            CallbackError::Exchange(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+}
+
+/// Errors when getting the user of a session
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq, displaydoc::Display)]
+#[non_exhaustive]
+pub enum SessionError {
+    /// not logged in
+    NoSession,
+    /// session has expired
+    Expired,
+}
+
+impl SessionError {
+    /// Status code to respond with
+    pub fn status(&self) -> StatusCode { StatusCode::UNAUTHORIZED }
+}
+
+/// In-memory sessions of logged in users, identified by a cookie.
+///
+/// Sessions end when the user token expires or after the [time to live](Sessions::set_ttl), at most [`max_len`](Sessions::set_max_len) sessions are stored.
+/// When there are too many sessions, the oldest session is ended to make room.
+///
+/// Cloning is cheap and the clones share the sessions.
+#[derive(Debug, Clone)]
+pub struct Sessions {
+    cookie_name: Arc<str>,
+    ttl: Duration,
+    max_len: usize,
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+}
+
+#[derive(Debug)]
+struct Session {
+    token: UserToken,
+    created: Instant,
+}
+
+impl Default for Sessions {
+    fn default() -> Self { Self::new("twitch_session") }
+}
+
+impl Sessions {
+    /// Create sessions identified by the cookie `cookie_name`
+    ///
+    /// Sessions live for at most 7 days, and at most 100 000 sessions are stored.
+    pub fn new(cookie_name: &str) -> Self {
+        Self {
+            cookie_name: cookie_name.into(),
+            ttl: Duration::from_secs(7 * 24 * 60 * 60),
+            max_len: 100_000,
+            sessions: Arc::default(),
+        }
+    }
+
+    /// Set the time sessions live for, even if the user token hasn't expired
+    pub fn set_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Set the maximum number of sessions
+    pub fn set_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len.max(1);
+        self
+    }
+
+    /// Get the name of the session cookie
+    pub fn cookie_name(&self) -> &str { &self.cookie_name }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Session>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn is_expired(&self, session: &Session) -> bool {
+        session.token.is_elapsed() || session.created.elapsed() >= self.ttl
+    }
+
+    /// Start a session for a user, returning the session id
+    ///
+    /// If there are too many sessions, expired sessions are removed, and then the oldest session if needed.
+    pub fn insert(&self, token: UserToken) -> String {
+        use rand::RngExt as _;
+
+        let mut random_bytes = [0u8; 32];
+        rand::rng().fill(&mut random_bytes);
+        let id = URL_SAFE_NO_PAD.encode(random_bytes);
+        let mut sessions = self.lock();
+        if sessions.len() >= self.max_len {
+            sessions.retain(|_, session| !self.is_expired(session));
+        }
+        if sessions.len() >= self.max_len {
+            let oldest = sessions
+                .iter()
+                .min_by_key(|(_, session)| session.created)
+                .map(|(id, _)| id.clone());
+            if let Some(oldest) = oldest {
+                sessions.remove(&oldest);
+            }
+        }
+        sessions.insert(
+            id.clone(),
+            Session {
+                token,
+                created: Instant::now(),
+            },
+        );
+        id
+    }
+
+    /// Get the user token of a session
+    ///
+    /// Expired sessions are removed.
+    pub fn get(&self, id: &str) -> Result<UserToken, SessionError> {
+        let mut sessions = self.lock();
+        let session = sessions.get(id).ok_or(SessionError::NoSession)?;
+        if self.is_expired(session) {
+            sessions.remove(id);
+            return Err(SessionError::Expired);
+        }
+        Ok(session.token.clone())
+    }
+
+    /// Replace the user token of a session, e.g. after [refreshing](TwitchToken::refresh_token) it
+    pub fn update(&self, id: &str, token: UserToken) -> Result<(), SessionError> {
+        let mut sessions = self.lock();
+        let session = sessions.get_mut(id).ok_or(SessionError::NoSession)?;
+        session.token = token;
+        Ok(())
+    }
+
+    /// End a session, returning its user token
+    pub fn remove(&self, id: &str) -> Option<UserToken> {
+        self.lock().remove(id).map(|session| session.token)
+    }
+
+    /// End all sessions of a user, returning their user tokens
+    pub fn remove_user(&self, user_id: &UserIdRef) -> Vec<UserToken> {
+        let mut tokens = vec![];
+        self.lock().retain(|_, session| {
+            if session.token.user_id == *user_id {
+                tokens.push(session.token.clone());
+                false
+            } else {
+                true
+            }
+        });
+        tokens
+    }
+
+    /// Returns the number of sessions
+    pub fn len(&self) -> usize { self.lock().len() }
+
+    /// Returns true if there are no sessions
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Get the session id from the value of a `Cookie` header
+    pub fn session_id<'a>(&self, cookie_header: &'a str) -> Option<&'a str> {
+        cookie_value(cookie_header, &self.cookie_name)
+    }
+
+    /// Get the user token of the session in the `Cookie` headers
+    pub fn from_headers(&self, headers: &http::HeaderMap) -> Result<UserToken, SessionError> {
+        self.from_cookies(
+            headers
+                .get_all(http::header::COOKIE)
+                .iter()
+                .filter_map(|h| h.to_str().ok()),
+        )
+    }
+
+    /// Get the user token of the session in the values of `Cookie` headers
+    pub fn from_cookies<'a>(
+        &self,
+        cookie_headers: impl IntoIterator<Item = &'a str>,
+    ) -> Result<UserToken, SessionError> {
+        let id = cookie_headers
+            .into_iter()
+            .find_map(|h| self.session_id(h))
+            .ok_or(SessionError::NoSession)?;
+        self.get(id)
+    }
+
+    /// Create the value of a `Set-Cookie` header for a session
+    pub fn cookie(&self, id: &str, secure: bool) -> String {
+        format!(
+            "{}={id}; Path=/; HttpOnly; SameSite=Lax{}",
+            self.cookie_name,
+            if secure { "; Secure" } else { "" }
+        )
+    }
+}
+
+/// Get the value of the cookie `name` from the value of a `Cookie` header
+fn cookie_value<'a>(cookie_header: &'a str, name: &str) -> Option<&'a str> {
+    cookie_header.split(';').find_map(|cookie| {
+        let (cookie_name, value) = cookie.trim().split_once('=')?;
+        (cookie_name == name).then_some(value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions() {
+        let sessions = Sessions::new("session");
+        let token = UserToken::from_existing_unchecked(
+            crate::AccessToken::from("token"),
+            None,
+            "clientid",
+            None,
+            "justintv".into(),
+            "1337".into(),
+            None,
+            Some(Duration::from_secs(60)),
+        );
+        let id = sessions.insert(token.clone());
+        let mut headers = http::HeaderMap::new();
+        headers.insert(
+            http::header::COOKIE,
+            format!("other=1; session={id}").parse().unwrap(),
+        );
+        assert_eq!(
+            sessions.from_headers(&headers).unwrap().login.as_str(),
+            "justintv"
+        );
+        assert!(sessions.remove(&id).is_some());
+        assert_eq!(
+            sessions.from_headers(&headers).unwrap_err(),
+            SessionError::NoSession
+        );
+        sessions.insert(token.clone());
+        sessions.insert(token);
+        assert_eq!(sessions.remove_user("1234".into()).len(), 0);
+        assert_eq!(sessions.remove_user("1337".into()).len(), 2);
+        assert!(sessions.is_empty());
+        assert_eq!(
+            sessions.from_headers(&headers).unwrap_err(),
+            SessionError::NoSession
+        );
+        assert_eq!(
+            sessions.cookie("abc", true),
+            "session=abc; Path=/; HttpOnly; SameSite=Lax; Secure"
+        );
+    }
+
+    #[test]
+    fn sessions_bounded() {
+        let token = UserToken::from_existing_unchecked(
+            crate::AccessToken::from("token"),
+            None,
+            "clientid",
+            None,
+            "justintv".into(),
+            "1337".into(),
+            None,
+            None,
+        );
+        let sessions = Sessions::new("session").set_max_len(2);
+        let ids = [(); 3].map(|_| {
+            let id = sessions.insert(token.clone());
+            std::thread::sleep(Duration::from_millis(1));
+            id
+        });
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions.get(&ids[0]).unwrap_err(), SessionError::NoSession);
+        assert!(sessions.get(&ids[1]).is_ok());
+        assert!(sessions.get(&ids[2]).is_ok());
+
+        let sessions = Sessions::new("session").set_ttl(Duration::ZERO);
+        let id = sessions.insert(token);
+        assert_eq!(sessions.get(&id).unwrap_err(), SessionError::Expired);
+        assert!(sessions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn logout_everywhere() {
+        let client = crate::client::mock::MockClient::default();
+        let auth = TwitchAuth::new(
+            client.clone(),
+            "clientid",
+            "clientsecret",
+            url::Url::parse("http://localhost/auth/callback").unwrap(),
+        );
+        let mut token = crate::client::mock::user_token("clientid");
+        token.refresh_token = Some("refresh".into());
+        auth.start_session(token.clone());
+        auth.start_session(token);
+        let results = auth.logout_everywhere("1337".into(), 2).await;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(
+            client.uris().len(),
+            4,
+            "access and refresh tokens are revoked"
+        );
+        assert!(auth.sessions().is_empty());
+    }
+}
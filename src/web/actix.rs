@@ -0,0 +1,426 @@
+//! Login and callback handlers for [actix-web](https://docs.rs/actix-web)
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! # fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! # let client = twitch_oauth2::client::DummyClient; stringify!(
+//! let client = reqwest::Client::new();
+//! # );
+//! use actix_web::{web, App};
+//! use twitch_oauth2::web::{actix::TwitchUser, TwitchAuth};
+//!
+//! let auth = web::Data::new(TwitchAuth::new(
+//!     client,
+//!     "myclientid",
+//!     "myclientsecret",
+//!     url::Url::parse("http://localhost:8080/auth/callback")?,
+//! ));
+//! let sessions = auth.sessions().clone();
+//!
+//! let app = App::new()
+//!     .app_data(sessions)
+//!     .service(twitch_oauth2::web::actix::scope("/auth", auth))
+//!     .route(
+//!         "/",
+//!         web::get().to(|TwitchUser(token): TwitchUser| async move {
+//!             format!("Hello {}", token.login)
+//!         }),
+//!     );
+//! # Ok(()) }
+//! ```
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+
+use actix_web::{
+    dev::Payload,
+    http::{header, StatusCode},
+    web::{self, Data},
+    FromRequest, HttpRequest, HttpResponse, ResponseError,
+};
+
+use super::{CallbackError, CallbackQuery, PendingLogin, SessionError, Sessions, TwitchAuth};
+use crate::{
+    client::Client,
+    tokens::{errors::ValidationError, UserTokenBuilder},
+    TwitchToken, UserToken, ValidatedToken,
+};
+
+/// Create a scope at `path` with a `/login` handler redirecting the user to twitch, and a `/callback` handler completing the login.
+///
+/// The callback handler starts a [session](Sessions) for the user, which can be retrieved with [`TwitchUser`].
+/// The [redirect url](TwitchAuth::new) has to point to the callback handler.
+pub fn scope<C: Client + 'static>(path: &str, auth: Data<TwitchAuth<C>>) -> actix_web::Scope {
+    web::scope(path)
+        .app_data(auth)
+        .route("/login", web::get().to(login::<C>))
+        .route("/callback", web::get().to(callback::<C>))
+}
+
+async fn login<C: Client + 'static>(auth: Data<TwitchAuth<C>>) -> HttpResponse {
+    let (url, cookie) = auth.login().await;
+    HttpResponse::SeeOther()
+        .insert_header((header::SET_COOKIE, cookie))
+        .insert_header((header::LOCATION, url.as_str()))
+        .finish()
+}
+
+async fn callback<C: Client + 'static>(
+    callback: TwitchCallback<C>,
+) -> Result<HttpResponse, CallbackError<C::Error>> {
+    let auth = callback.auth.clone();
+    let token = callback.finish().await?;
+    Ok(HttpResponse::SeeOther()
+        .insert_header((header::SET_COOKIE, auth.start_session(token)))
+        .insert_header((header::LOCATION, auth.login_redirect()))
+        .finish())
+}
+
+fn status(status: http::StatusCode) -> StatusCode {
+    StatusCode::from_u16(status.as_u16()).expect("status codes are valid in both http versions")
+}
+
+impl<RE: std::error::Error + Send + Sync + 'static> ResponseError for CallbackError<RE> {
+    fn status_code(&self) -> StatusCode { status(self.status()) }
+}
+
+impl ResponseError for SessionError {
+    fn status_code(&self) -> StatusCode { status(self.status()) }
+}
+
+/// Extractor for a login returning to the callback, restoring the [builder](UserTokenBuilder) from the `state`.
+///
+/// Requires the [`TwitchAuth`] to be added with [`App::app_data`](actix_web::App::app_data), which [`scope`] does.
+/// Use this to write your own callback handler, rejects the request if the state is unknown, doesn't match the login cookie set by
+/// [`TwitchAuth::login`] or the user denied the authorization.
+pub struct TwitchCallback<C: Client> {
+    pending: PendingLogin,
+    auth: Data<TwitchAuth<C>>,
+}
+
+impl<C: Client> std::fmt::Debug for TwitchCallback<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TwitchCallback")
+            .field("pending", &self.pending)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C: Client> TwitchCallback<C> {
+    /// Get the builder restored from the state
+    pub fn builder(&self) -> &UserTokenBuilder { &self.pending.builder }
+
+    /// Get the pending login
+    pub fn into_pending(self) -> PendingLogin { self.pending }
+
+    /// Exchange the code for a user token, see [`TwitchAuth::finish_login`]
+    pub async fn finish(self) -> Result<UserToken, CallbackError<C::Error>> {
+        self.auth.finish_login(self.pending).await
+    }
+}
+
+impl<C: Client + 'static> FromRequest for TwitchCallback<C> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let auth = req.app_data::<Data<TwitchAuth<C>>>().cloned();
+        let query = web::Query::<CallbackQuery>::from_query(req.query_string());
+        let cookies: Vec<String> = req
+            .headers()
+            .get_all(header::COOKIE)
+            .filter_map(|h| h.to_str().ok().map(str::to_owned))
+            .collect();
+        Box::pin(async move {
+            let auth = auth.ok_or_else(|| {
+                actix_web::error::ErrorInternalServerError("TwitchAuth is not configured")
+            })?;
+            let web::Query(query) = query?;
+            let pending = auth
+                .pending_login(&query, cookies.iter().map(String::as_str))
+                .await?;
+            Ok(TwitchCallback { pending, auth })
+        })
+    }
+}
+
+/// Extractor for the [user token](UserToken) of the logged in user.
+///
+/// Requires [`Sessions`] to be added with [`App::app_data`](actix_web::App::app_data).
+/// Rejects the request with `401 Unauthorized` if the user isn't logged in or the token has expired.
+#[derive(Debug, Clone)]
+pub struct TwitchUser(pub UserToken);
+
+impl FromRequest for TwitchUser {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let Some(sessions) = req.app_data::<Sessions>() else {
+            return ready(Err(actix_web::error::ErrorInternalServerError(
+                "Sessions are not configured",
+            )));
+        };
+        let cookies = req
+            .headers()
+            .get_all(header::COOKIE)
+            .filter_map(|h| h.to_str().ok());
+        ready(
+            sessions
+                .from_cookies(cookies)
+                .map(TwitchUser)
+                .map_err(Into::into),
+        )
+    }
+}
+
+/// Extractor for the [user token](UserToken) of the logged in user, validated with twitch.
+///
+/// Requires [`Sessions`] and the [`TwitchAuth`] to be added with [`App::app_data`](actix_web::App::app_data), the client of the [`TwitchAuth`] is used to validate the token.
+/// If a [`ValidationCache`](crate::ValidationCache) is added as well, validations are cached in it.
+/// Rejects the request with `401 Unauthorized` if the user isn't logged in or the token is not authorized anymore,
+/// and with `502 Bad Gateway` if twitch can't be reached.
+pub struct ValidatedTwitchUser<C> {
+    token: UserToken,
+    validated: ValidatedToken,
+    _client: std::marker::PhantomData<fn() -> C>,
+}
+
+impl<C> std::fmt::Debug for ValidatedTwitchUser<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ValidatedTwitchUser")
+            .field("token", &self.token)
+            .field("validated", &self.validated)
+            .finish()
+    }
+}
+
+impl<C> ValidatedTwitchUser<C> {
+    /// Get the user token
+    pub fn token(&self) -> &UserToken { &self.token }
+
+    /// Get the validation of the token
+    pub fn validated(&self) -> &ValidatedToken { &self.validated }
+
+    /// Get the user token and its validation
+    pub fn into_parts(self) -> (UserToken, ValidatedToken) { (self.token, self.validated) }
+}
+
+impl<C: Client + 'static> FromRequest for ValidatedTwitchUser<C> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let auth = req.app_data::<Data<TwitchAuth<C>>>().cloned();
+        #[cfg(feature = "validation_cache")]
+        let cache = req.app_data::<crate::ValidationCache>().cloned();
+        let token = TwitchUser::from_request(req, payload).into_inner();
+        Box::pin(async move {
+            let auth = auth.ok_or_else(|| {
+                actix_web::error::ErrorInternalServerError("TwitchAuth is not configured")
+            })?;
+            let TwitchUser(token) = token?;
+            #[cfg(feature = "validation_cache")]
+            let validated = match cache {
+                Some(cache) => cache.validate_token(token.token(), &auth.client).await,
+                None => token.validate_token(&auth.client).await,
+            };
+            #[cfg(not(feature = "validation_cache"))]
+            let validated = token.validate_token(&auth.client).await;
+            match validated {
+                Ok(validated) => Ok(ValidatedTwitchUser {
+                    token,
+                    validated,
+                    _client: std::marker::PhantomData,
+                }),
+                Err(ValidationError::NotAuthorized) => Err(actix_web::error::ErrorUnauthorized(
+                    "token is not authorized",
+                )),
+                Err(e) => Err(actix_web::error::ErrorBadGateway(e)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::mock::{user_token, MockClient};
+    use actix_web::{test, App};
+
+    fn auth(client: MockClient) -> Data<TwitchAuth<MockClient>> {
+        Data::new(
+            TwitchAuth::new(
+                client,
+                "clientid",
+                "clientsecret",
+                url::Url::parse("http://localhost/auth/callback").unwrap(),
+            )
+            .set_scopes(vec![crate::Scope::ChatRead]),
+        )
+    }
+
+    async fn hello(TwitchUser(token): TwitchUser) -> String { token.login.to_string() }
+
+    async fn restored(callback: TwitchCallback<MockClient>) -> String {
+        callback.builder().redirect_url.to_string()
+    }
+
+    #[actix_web::test]
+    async fn login_and_callback() {
+        let auth = auth(MockClient::default());
+        let app = test::init_service(App::new().service(scope("/auth", auth))).await;
+
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get().uri("/auth/login").to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+        let location = resp
+            .headers()
+            .get(header::LOCATION)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        let location = url::Url::parse(location).unwrap();
+        assert!(location
+            .query_pairs()
+            .any(|(k, v)| k == "scope" && v == "chat:read"));
+        let set_cookie = resp.headers().get(header::SET_COOKIE).unwrap();
+        assert!(set_cookie
+            .to_str()
+            .unwrap()
+            .contains("HttpOnly; SameSite=Lax"));
+
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/auth/callback?code=abc&state=unknown")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/auth/callback?error=access_denied")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn callback_extractor_checks_cookie() {
+        let client = MockClient::default();
+        let auth = auth(client.clone());
+        let (url, cookie) = auth.login().await;
+        let state = url
+            .query_pairs()
+            .find(|(k, _)| k == "state")
+            .unwrap()
+            .1
+            .into_owned();
+        let cookie = cookie.split(';').next().unwrap().to_owned();
+        let app = test::init_service(
+            App::new()
+                .app_data(auth)
+                .route("/auth/callback", web::get().to(restored)),
+        )
+        .await;
+        let uri = |state: &str| {
+            let mut uri = url::Url::parse("http://localhost/auth/callback?code=abc").unwrap();
+            uri.query_pairs_mut().append_pair("state", state);
+            format!("/auth/callback?{}", uri.query().unwrap())
+        };
+
+        let req = test::TestRequest::get().uri(&uri(&state)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "missing cookie");
+        let req = test::TestRequest::get()
+            .uri(&uri(&state))
+            .insert_header((header::COOKIE, "twitch_login=other"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.status(),
+            StatusCode::BAD_REQUEST,
+            "cookie of another browser"
+        );
+
+        let req = test::TestRequest::get()
+            .uri(&uri(&state))
+            .insert_header((header::COOKIE, cookie))
+            .to_request();
+        assert_eq!(
+            test::call_and_read_body(&app, req).await,
+            "http://localhost/auth/callback"
+        );
+        assert!(client.uris().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn extractor() {
+        let auth = auth(MockClient::default());
+        let sessions = auth.sessions().clone();
+        let app = test::init_service(
+            App::new()
+                .app_data(sessions.clone())
+                .service(scope("/auth", auth))
+                .route("/", web::get().to(hello)),
+        )
+        .await;
+
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let id = sessions.insert(user_token("clientid"));
+        let req = test::TestRequest::get()
+            .uri("/")
+            .insert_header((header::COOKIE, format!("{}={id}", sessions.cookie_name())))
+            .to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "justintv");
+    }
+
+    #[actix_web::test]
+    #[cfg(feature = "validation_cache")]
+    async fn validated_extractor() {
+        async fn validated(user: ValidatedTwitchUser<MockClient>) -> String {
+            user.validated().login.as_ref().unwrap().to_string()
+        }
+
+        let client = MockClient::default()
+            .respond(
+                200,
+                r#"{"client_id":"clientid","login":"justintv","user_id":"1337","scopes":["chat:read"],"expires_in":3600}"#,
+            )
+            .respond(401, r#"{"status":401,"message":"invalid access token"}"#);
+        let auth = auth(client.clone());
+        let sessions = auth.sessions().clone();
+        let cache = crate::ValidationCache::new(std::time::Duration::from_secs(60));
+        let app = test::init_service(
+            App::new()
+                .app_data(sessions.clone())
+                .app_data(auth)
+                .app_data(cache.clone())
+                .route("/", web::get().to(validated)),
+        )
+        .await;
+
+        let id = sessions.insert(user_token("clientid"));
+        let req = || {
+            test::TestRequest::get()
+                .uri("/")
+                .insert_header((header::COOKIE, format!("{}={id}", sessions.cookie_name())))
+                .to_request()
+        };
+        assert_eq!(test::call_and_read_body(&app, req()).await, "justintv");
+        test::call_and_read_body(&app, req()).await;
+        assert_eq!(client.uris().len(), 1, "the validation is cached");
+
+        cache.clear();
+        let resp = test::call_service(&app, req()).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,239 @@
+//! Login and callback handlers for [axum](https://docs.rs/axum)
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! # let client = twitch_oauth2::client::DummyClient; stringify!(
+//! let client = reqwest::Client::new();
+//! # );
+//! use std::sync::Arc;
+//!
+//! use axum::{routing::get, Router};
+//! use twitch_oauth2::web::{axum::TwitchUser, TwitchAuth};
+//!
+//! let auth = TwitchAuth::new(
+//!     client,
+//!     "myclientid",
+//!     "myclientsecret",
+//!     url::Url::parse("http://localhost:3000/auth/callback")?,
+//! );
+//! let sessions = auth.sessions().clone();
+//!
+//! let app = Router::new()
+//!     .route(
+//!         "/",
+//!         get(|TwitchUser(token): TwitchUser| async move { format!("Hello {}", token.login) }),
+//!     )
+//!     .nest("/auth", twitch_oauth2::web::axum::router(Arc::new(auth)))
+//!     .with_state(sessions);
+//! # let _: Router = app;
+//! # Ok(()) }
+//! ```
+use std::sync::Arc;
+
+use ::axum::{
+    extract::{FromRef, FromRequestParts, Query, State},
+    http::{header, request::Parts, HeaderMap},
+    response::{IntoResponse, Redirect, Response},
+    routing::get,
+    Router,
+};
+
+use super::{CallbackError, CallbackQuery, SessionError, Sessions, TwitchAuth};
+use crate::{client::Client, UserToken};
+
+/// Create a router with a `/login` handler redirecting the user to twitch, and a `/callback` handler completing the login.
+///
+/// The callback handler starts a [session](Sessions) for the user, which can be retrieved with [`TwitchUser`].
+/// The [redirect url](TwitchAuth::new) has to point to the callback handler.
+pub fn router<C, S>(auth: Arc<TwitchAuth<C>>) -> Router<S>
+where
+    C: Client + 'static,
+    S: Clone + Send + Sync + 'static, {
+    Router::new()
+        .route("/login", get(login::<C>))
+        .route("/callback", get(callback::<C>))
+        .with_state(auth)
+}
+
+async fn login<C: Client + 'static>(State(auth): State<Arc<TwitchAuth<C>>>) -> Response {
+    let (url, cookie) = auth.login().await;
+    ([(header::SET_COOKIE, cookie)], Redirect::to(url.as_str())).into_response()
+}
+
+async fn callback<C: Client + 'static>(
+    State(auth): State<Arc<TwitchAuth<C>>>,
+    Query(query): Query<CallbackQuery>,
+    headers: HeaderMap,
+) -> Result<Response, CallbackError<C::Error>> {
+    let cookies = headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|h| h.to_str().ok());
+    let cookie = auth.login_session(&query, cookies).await?;
+    Ok((
+        [(header::SET_COOKIE, cookie)],
+        Redirect::to(auth.login_redirect()),
+    )
+        .into_response())
+}
+
+impl<RE: std::error::Error + Send + Sync + 'static> IntoResponse for CallbackError<RE> {
+    fn into_response(self) -> Response { (self.status(), self.to_string()).into_response() }
+}
+
+impl IntoResponse for SessionError {
+    fn into_response(self) -> Response { (self.status(), self.to_string()).into_response() }
+}
+
+/// Extractor for the [user token](UserToken) of the logged in user.
+///
+/// Requires [`Sessions`] to be extractable from the state with [`FromRef`].
+/// Rejects the request with `401 Unauthorized` if the user isn't logged in or the token has expired.
+#[derive(Debug, Clone)]
+pub struct TwitchUser(pub UserToken);
+
+impl<S> FromRequestParts<S> for TwitchUser
+where
+    Sessions: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = SessionError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Sessions::from_ref(state)
+            .from_headers(&parts.headers)
+            .map(TwitchUser)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::mock::{user_token, MockClient};
+    use ::axum::{body::Body, http::Request, http::StatusCode};
+    use tower::ServiceExt;
+
+    fn app(client: MockClient) -> (Router, Sessions) {
+        let auth = TwitchAuth::new(
+            client,
+            "clientid",
+            "clientsecret",
+            url::Url::parse("http://localhost/auth/callback").unwrap(),
+        )
+        .set_scopes(vec![crate::Scope::ChatRead]);
+        let sessions = auth.sessions().clone();
+        let app = Router::new()
+            .route(
+                "/",
+                get(|TwitchUser(token): TwitchUser| async move { token.login.to_string() }),
+            )
+            .nest("/auth", router(Arc::new(auth)))
+            .with_state(sessions.clone());
+        (app, sessions)
+    }
+
+    async fn get_req(app: Router, uri: &str, cookie: Option<&str>) -> Response {
+        let mut req = Request::get(uri);
+        if let Some(cookie) = cookie {
+            req = req.header(header::COOKIE, cookie);
+        }
+        app.oneshot(req.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    /// Start a login, returning the state and the login cookie
+    async fn start_login(app: Router) -> (String, String) {
+        let resp = get_req(app, "/auth/login", None).await;
+        assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+        let location = resp.headers()[header::LOCATION].to_str().unwrap();
+        let location = url::Url::parse(location).unwrap();
+        assert!(location
+            .query_pairs()
+            .any(|(k, v)| k == "scope" && v == "chat:read"));
+        let state = location
+            .query_pairs()
+            .find(|(k, _)| k == "state")
+            .unwrap()
+            .1
+            .into_owned();
+        let set_cookie = resp.headers()[header::SET_COOKIE].to_str().unwrap();
+        assert!(set_cookie.contains("HttpOnly; SameSite=Lax"));
+        let cookie = set_cookie.split(';').next().unwrap().to_owned();
+        (state, cookie)
+    }
+
+    fn callback_uri(state: &str) -> String {
+        let mut uri = url::Url::parse("http://localhost/auth/callback?code=abc").unwrap();
+        uri.query_pairs_mut().append_pair("state", state);
+        format!("/auth/callback?{}", uri.query().unwrap())
+    }
+
+    #[tokio::test]
+    async fn login_and_callback() {
+        let client = MockClient::default()
+            .respond(
+                200,
+                r#"{"access_token":"token","expires_in":3600,"token_type":"bearer"}"#,
+            )
+            .respond(
+                200,
+                r#"{"client_id":"clientid","login":"justintv","user_id":"1337","scopes":["chat:read"],"expires_in":3600}"#,
+            );
+        let (app, sessions) = app(client.clone());
+        let (state, cookie) = start_login(app.clone()).await;
+
+        let resp = get_req(app.clone(), "/auth/callback?code=abc&state=unknown", None).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = get_req(
+            app.clone(),
+            "/auth/callback?error=access_denied&error_description=nope",
+            None,
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let resp = get_req(app.clone(), &callback_uri(&state), Some(&cookie)).await;
+        assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+        assert_eq!(resp.headers()[header::LOCATION], "/");
+        let session = resp.headers()[header::SET_COOKIE].to_str().unwrap();
+        let id = sessions
+            .session_id(session.split(';').next().unwrap())
+            .unwrap();
+        assert_eq!(sessions.get(id).unwrap().login.as_str(), "justintv");
+        assert_eq!(client.uris().len(), 2);
+
+        let resp = get_req(app, &callback_uri(&state), Some(&cookie)).await;
+        assert_eq!(
+            resp.status(),
+            StatusCode::BAD_REQUEST,
+            "states are used once"
+        );
+    }
+
+    #[tokio::test]
+    async fn callback_from_other_browser() {
+        let client = MockClient::default();
+        let (app, _) = app(client.clone());
+        let (state, _) = start_login(app.clone()).await;
+        let (_, other_cookie) = start_login(app.clone()).await;
+
+        let resp = get_req(app.clone(), &callback_uri(&state), None).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = get_req(app.clone(), &callback_uri(&state), Some(&other_cookie)).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(client.uris().is_empty(), "the code is never exchanged");
+    }
+
+    #[tokio::test]
+    async fn extractor() {
+        let (app, sessions) = app(MockClient::default());
+        let resp = get_req(app.clone(), "/", None).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let id = sessions.insert(user_token("clientid"));
+        let cookie = format!("{}={id}", sessions.cookie_name());
+        let resp = get_req(app, "/", Some(&cookie)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,433 @@
+//! Bearer token authentication for [tower](https://docs.rs/tower) services
+//!
+//! [`BearerAuthLayer`] authenticates requests to your own APIs with twitch tokens sent by clients,
+//! in the `Authorization: Bearer <token>` or `Authorization: OAuth <token>` header.
+//! Tokens are [validated](crate::AccessTokenRef::validate_token) with twitch, and the [`ValidatedToken`] is inserted into the request extensions.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # fn run() {
+//! # let client = twitch_oauth2::client::DummyClient; stringify!(
+//! let client = reqwest::Client::new();
+//! # );
+//! use std::time::Duration;
+//! use twitch_oauth2::{validator, web::tower::BearerAuthLayer, Scope, ValidatedToken};
+//!
+//! let layer = BearerAuthLayer::new(client, Duration::from_secs(60))
+//!     .set_client_id("myclientid")
+//!     .set_validator(validator!(Scope::UserReadEmail));
+//!
+//! // use with axum
+//! # stringify!(
+//! let app = axum::Router::new()
+//!     .route(
+//!         "/me",
+//!         get(|Extension(token): Extension<ValidatedToken>| async move {
+//!             format!("{:?}", token.login)
+//!         }),
+//!     )
+//!     .layer(layer);
+//! # );
+//! # }
+//! ```
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use ::tower::{Layer, Service};
+use http::{header, HeaderValue, Request, Response, StatusCode};
+
+use crate::{
+    client::Client, tokens::errors::ValidationError, AccessTokenRef, ClientId, ValidatedToken,
+    ValidationCache, Validator,
+};
+
+/// A [`Layer`] authenticating requests with twitch tokens.
+///
+/// Requests without a valid token are rejected with `401 Unauthorized`, and requests with a token not matching the
+/// [validator](BearerAuthLayer::set_validator) with `403 Forbidden`. If twitch can't be reached, requests are rejected with `502 Bad Gateway`.
+///
+/// Validations are cached in a [`ValidationCache`], including tokens that are not authorized.
+#[derive(Debug)]
+pub struct BearerAuthLayer<C> {
+    auth: Arc<BearerAuth<C>>,
+}
+
+impl<C> Clone for BearerAuthLayer<C> {
+    fn clone(&self) -> Self {
+        Self {
+            auth: self.auth.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BearerAuth<C> {
+    client: Arc<C>,
+    client_id: Option<ClientId>,
+    validator: Option<Validator>,
+    cache: ValidationCache,
+}
+
+impl<C> Clone for BearerAuth<C> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            client_id: self.client_id.clone(),
+            validator: self.validator.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+impl<C: Client> BearerAuthLayer<C> {
+    /// Create a layer validating tokens with `client`, caching validations for `ttl`.
+    pub fn new(client: C, ttl: Duration) -> Self {
+        Self {
+            auth: Arc::new(BearerAuth {
+                client: Arc::new(client),
+                client_id: None,
+                validator: None,
+                cache: ValidationCache::new(ttl),
+            }),
+        }
+    }
+
+    fn auth_mut(&mut self) -> &mut BearerAuth<C> {
+        // only copies the configuration if the layer has been cloned, the client and cache are shared
+        Arc::make_mut(&mut self.auth)
+    }
+
+    /// Only accept tokens created for `client_id`
+    pub fn set_client_id(mut self, client_id: impl Into<ClientId>) -> Self {
+        self.auth_mut().client_id = Some(client_id.into());
+        self
+    }
+
+    /// Use `cache` for validations, to share it with the rest of your application
+    pub fn set_cache(mut self, cache: ValidationCache) -> Self {
+        self.auth_mut().cache = cache;
+        self
+    }
+
+    /// Only accept tokens with scopes matching `validator`
+    pub fn set_validator(mut self, validator: Validator) -> Self {
+        self.auth_mut().validator = Some(validator);
+        self
+    }
+}
+
+impl<S, C> Layer<S> for BearerAuthLayer<C> {
+    type Service = BearerAuthService<S, C>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BearerAuthService {
+            inner,
+            auth: self.auth.clone(),
+        }
+    }
+}
+
+/// Service created by [`BearerAuthLayer`]
+#[derive(Debug)]
+pub struct BearerAuthService<S, C> {
+    inner: S,
+    auth: Arc<BearerAuth<C>>,
+}
+
+impl<S: Clone, C> Clone for BearerAuthService<S, C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            auth: self.auth.clone(),
+        }
+    }
+}
+
+/// Errors when authenticating a request with [`BearerAuthLayer`]
+#[derive(Debug, Clone, displaydoc::Display)]
+#[non_exhaustive]
+pub enum BearerAuthError {
+    /// request has no bearer token
+    MissingToken,
+    /// token is not valid
+    InvalidToken,
+    /// token was created for another client id
+    WrongClientId,
+    /// token is missing scopes: {missing}
+    MissingScopes {
+        /// Validator for the scopes that are missing
+        missing: Validator,
+    },
+    /// could not validate token
+    // shared, so the error can be cloned into the response extensions
+    Validation(Arc<dyn std::error::Error + Send + Sync>),
+}
+
+// not derived, the source is the error in the `Arc` and not the `Arc` itself
+impl std::error::Error for BearerAuthError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BearerAuthError::Validation(e) => Some(&**e),
+            _ => None,
+        }
+    }
+}
+
+impl BearerAuthError {
+    /// Status code to respond with
+    pub fn status(&self) -> StatusCode {
+        match self {
+            BearerAuthError::MissingToken
+            | BearerAuthError::InvalidToken
+            | BearerAuthError::WrongClientId => StatusCode::UNAUTHORIZED,
+            BearerAuthError::MissingScopes { .. } => StatusCode::FORBIDDEN,
+            BearerAuthError::Validation(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    /// Value of the `WWW-Authenticate` header to respond with, see [RFC 6750](https://datatracker.ietf.org/doc/html/rfc6750#section-3)
+    pub fn www_authenticate(&self) -> Option<HeaderValue> {
+        match self {
+            BearerAuthError::MissingToken => Some(HeaderValue::from_static("Bearer")),
+            BearerAuthError::InvalidToken | BearerAuthError::WrongClientId => {
+                Some(HeaderValue::from_static("Bearer error=\"invalid_token\""))
+            }
+            BearerAuthError::MissingScopes { .. } => Some(HeaderValue::from_static(
+                "Bearer error=\"insufficient_scope\"",
+            )),
+            BearerAuthError::Validation(_) => None,
+        }
+    }
+
+    fn into_response<B: Default>(self) -> Response<B> {
+        let mut response = Response::new(B::default());
+        *response.status_mut() = self.status();
+        if let Some(value) = self.www_authenticate() {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, value);
+        }
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+/// Get the token from an `Authorization: Bearer <token>` or `Authorization: OAuth <token>` header
+pub fn bearer_token(headers: &http::HeaderMap) -> Option<&AccessTokenRef> {
+    let (scheme, token) = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .split_once(' ')?;
+    let token = token.trim();
+    ((scheme.eq_ignore_ascii_case("bearer") || scheme.eq_ignore_ascii_case("oauth"))
+        && !token.is_empty())
+    .then(|| AccessTokenRef::from_str(token))
+}
+
+impl<C: Client> BearerAuth<C> {
+    async fn validate(&self, token: &AccessTokenRef) -> Result<ValidatedToken, BearerAuthError> {
+        let validated = self
+            .cache
+            .validate_token(token, &*self.client)
+            .await
+            .map_err(|e| match e {
+                ValidationError::NotAuthorized => BearerAuthError::InvalidToken,
+                e => BearerAuthError::Validation(Arc::new(e)),
+            })?;
+        if self
+            .client_id
+            .as_ref()
+            .is_some_and(|id| id != &validated.client_id)
+        {
+            return Err(BearerAuthError::WrongClientId);
+        }
+        if let Some(missing) = self
+            .validator
+            .as_ref()
+            .and_then(|v| v.missing(validated.scopes.as_ref().unwrap_or(&crate::ScopeSet::new())))
+        {
+            return Err(BearerAuthError::MissingScopes { missing });
+        }
+        Ok(validated)
+    }
+}
+
+impl<S, C, B, ResBody> Service<Request<B>> for BearerAuthService<S, C>
+where
+    S: Service<Request<B>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send,
+    C: Client + 'static,
+    B: Send + 'static,
+    ResBody: Default,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = Response<ResBody>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<B>) -> Self::Future {
+        // take the service that was polled ready, leaving a clone in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let auth = self.auth.clone();
+        Box::pin(async move {
+            let Some(token) = bearer_token(req.headers()).map(ToOwned::to_owned) else {
+                return Ok(BearerAuthError::MissingToken.into_response());
+            };
+            match auth.validate(&token).await {
+                Ok(validated) => {
+                    req.extensions_mut().insert(validated);
+                    inner.call(req).await
+                }
+                Err(e) => Ok(e.into_response()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::tower::ServiceExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Debug, Default)]
+    struct ValidateClient {
+        calls: AtomicUsize,
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("no connection")]
+    struct NoConnection;
+
+    impl Client for ValidateClient {
+        type Error = NoConnection;
+
+        fn req(
+            &self,
+            request: http::Request<Vec<u8>>,
+        ) -> impl Future<Output = Result<http::Response<Vec<u8>>, Self::Error>> + Send + use<>
+        {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let response = match request.headers()[header::AUTHORIZATION].to_str().unwrap() {
+                "OAuth good" => Response::new(
+                    br#"{"client_id":"clientid","login":"justintv","scopes":["chat:read"],"user_id":"1337","expires_in":3600}"#
+                        .to_vec(),
+                ),
+                "OAuth down" => return std::future::ready(Err(NoConnection)),
+                _ => {
+                    let mut response = Response::new(
+                        br#"{"status":401,"message":"invalid access token"}"#.to_vec(),
+                    );
+                    *response.status_mut() = StatusCode::UNAUTHORIZED;
+                    response
+                }
+            };
+            std::future::ready(Ok(response))
+        }
+    }
+
+    async fn call(
+        layer: &BearerAuthLayer<ValidateClient>,
+        authorization: Option<&str>,
+    ) -> Response<String> {
+        let service = layer.layer(::tower::service_fn(|req: Request<()>| async move {
+            let validated = req.extensions().get::<ValidatedToken>().unwrap();
+            Ok::<_, std::convert::Infallible>(Response::new(
+                validated.login.as_ref().unwrap().to_string(),
+            ))
+        }));
+        let mut req = Request::new(());
+        if let Some(authorization) = authorization {
+            req.headers_mut()
+                .insert(header::AUTHORIZATION, authorization.parse().unwrap());
+        }
+        service.oneshot(req).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn bearer_auth() {
+        let layer = BearerAuthLayer::new(ValidateClient::default(), Duration::from_secs(60))
+            .set_client_id("clientid")
+            .set_validator(crate::validator!(crate::Scope::ChatRead));
+
+        let resp = call(&layer, Some("Bearer good")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "justintv");
+        assert_eq!(
+            call(&layer, Some("OAuth good")).await.status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            layer.auth.client.calls.load(Ordering::SeqCst),
+            1,
+            "validation should be cached"
+        );
+
+        let resp = call(&layer, None).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(resp.headers()[header::WWW_AUTHENTICATE], "Bearer");
+        assert_eq!(
+            call(&layer, Some("Bearer bad")).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            call(&layer, Some("Bearer bad")).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            layer.auth.client.calls.load(Ordering::SeqCst),
+            2,
+            "tokens that are not authorized should be cached"
+        );
+        assert_eq!(
+            call(&layer, Some("Basic good")).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let resp = call(&layer, Some("Bearer down")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+        let error = resp.extensions().get::<BearerAuthError>().unwrap();
+        let source = std::error::Error::source(error).unwrap();
+        assert!(source
+            .downcast_ref::<ValidationError<NoConnection>>()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn bearer_auth_checks() {
+        let layer = BearerAuthLayer::new(ValidateClient::default(), Duration::from_secs(60))
+            .set_client_id("otherclientid");
+        assert_eq!(
+            call(&layer, Some("Bearer good")).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let layer = BearerAuthLayer::new(ValidateClient::default(), Duration::from_secs(60))
+            .set_validator(crate::validator!(crate::Scope::UserReadEmail));
+        let resp = call(&layer, Some("Bearer good")).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert!(matches!(
+            resp.extensions().get::<BearerAuthError>(),
+            Some(BearerAuthError::MissingScopes { .. })
+        ));
+
+        let strict = layer.clone().set_client_id("otherclientid");
+        assert_eq!(
+            call(&strict, Some("Bearer good")).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            call(&layer, Some("Bearer good")).await.status(),
+            StatusCode::FORBIDDEN,
+            "configuring a clone doesn't change the original"
+        );
+    }
+}